@@ -119,7 +119,6 @@ impl App for Inox2dOpenglExampleApp {
 		renderer.clear();
 
 		let puppet = &mut self.model.puppet;
-		puppet.begin_set_params();
 		let t = scene_ctrl.current_elapsed();
 		let _ = puppet.set_named_param("Head:: Yaw-Pitch", Vec2::new(t.cos(), t.sin()));
 		puppet.end_set_params(scene_ctrl.dt());
@@ -118,7 +118,6 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 			renderer.borrow().clear();
 			{
 				let mut puppet = puppet.borrow_mut();
-				puppet.begin_set_params();
 				let t = scene_ctrl.borrow().current_elapsed();
 				puppet.set_named_param("Head:: Yaw-Pitch", Vec2::new(t.cos(), t.sin()));
 				puppet.end_set_params(scene_ctrl.borrow().dt());
@@ -41,7 +41,9 @@ pub async fn run(model: Model) {
 		.request_device(
 			&wgpu::DeviceDescriptor {
 				label: None,
-				required_features: wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER,
+				// BC7 textures can be uploaded without decoding them if supported
+				required_features: wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER
+					| (adapter.features() & wgpu::Features::TEXTURE_COMPRESSION_BC),
 				required_limits: wgpu::Limits::default(),
 			},
 			None,
@@ -89,7 +91,6 @@ pub async fn run(model: Model) {
 			} => {
 				scene_ctrl.update(&mut renderer.camera);
 
-				puppet.begin_set_params();
 				let t = scene_ctrl.current_elapsed();
 				let _ = puppet.set_named_param("Head:: Yaw-Pitch", vec2(t.cos(), t.sin()));
 				puppet.end_set_params(scene_ctrl.dt());
@@ -8,14 +8,14 @@ use std::mem;
 use std::ops::Deref;
 
 use gl_buffer::RenderCtxOpenglExt;
-use glam::{uvec2, Mat4, UVec2, Vec2, Vec3};
+use glam::{uvec2, Mat4, UVec2, Vec2};
 use glow::HasContext;
 use inox2d::texture::{decode_model_textures, TextureId};
 
 use inox2d::math::camera::Camera;
 use inox2d::model::{Model, ModelTexture};
-use inox2d::node::data::{BlendMode, Composite, Part};
-use inox2d::puppet::Puppet;
+use inox2d::node::data::{BlendMode, Composite, CustomNode, Part};
+use inox2d::puppet::PuppetInstance;
 use inox2d::render::{InoxRenderer, InoxRendererCommon, NodeRenderCtx, PartRenderCtx};
 
 use self::shader::ShaderCompileError;
@@ -109,6 +109,7 @@ impl GlCache {
 pub struct OpenglRenderer {
 	gl: glow::Context,
 	support_debug_extension: bool,
+	support_bc7_compression: bool,
 	pub camera: Camera,
 	pub viewport: UVec2,
 	cache: RefCell<GlCache>,
@@ -157,10 +158,14 @@ impl OpenglRenderer {
 		let composite_mask_shader = CompositeMaskShader::new(&gl)?;
 
 		let support_debug_extension = gl.supported_extensions().contains("GL_KHR_debug");
+		let support_bc7_compression = ["GL_ARB_texture_compression_bptc", "GL_EXT_texture_compression_bptc"]
+			.iter()
+			.any(|ext| gl.supported_extensions().contains(*ext));
 
 		let renderer = Self {
 			gl,
 			support_debug_extension,
+			support_bc7_compression,
 			camera: Camera::default(),
 			viewport: UVec2::default(),
 			cache: RefCell::new(GlCache::default()),
@@ -190,7 +195,7 @@ impl OpenglRenderer {
 
 	fn upload_model_textures(&mut self, model_textures: &[ModelTexture]) -> Result<(), TextureError> {
 		// decode textures in parallel
-		let shalltexs = decode_model_textures(model_textures.iter());
+		let shalltexs = decode_model_textures(model_textures.iter(), self.support_bc7_compression);
 
 		// upload textures
 		for (i, shalltex) in shalltexs.iter().enumerate() {
@@ -355,7 +360,7 @@ impl OpenglRenderer {
 impl InoxRenderer for OpenglRenderer {
 	type Error = OpenglRendererError;
 
-	fn prepare(&mut self, model: &Model) -> Result<(), Self::Error> {
+	fn prepare<T: CustomNode>(&mut self, model: &Model<T>) -> Result<(), Self::Error> {
 		unsafe { model.puppet.render_ctx.setup_gl_buffers(&self.gl, self.vao)? };
 
 		match self.upload_model_textures(&model.textures) {
@@ -411,7 +416,7 @@ impl InoxRenderer for OpenglRenderer {
 		todo!()
 	}
 
-	fn render(&self, puppet: &Puppet) {
+	fn render<T: CustomNode>(&self, puppet: &PuppetInstance<T>) {
 		let gl = &self.gl;
 		unsafe {
 			puppet.render_ctx.upload_deforms_to_gl(gl);
@@ -520,7 +525,7 @@ impl InoxRenderer for OpenglRenderer {
 			part_mask_shader.set_mvp(gl, mvp);
 
 			// frag uniforms
-			part_mask_shader.set_threshold(gl, part.draw_state.mask_threshold.clamp(0.0, 1.0));
+			part_mask_shader.set_threshold(gl, node_render_ctx.drawable_offset.clamped_mask_threshold());
 		} else {
 			let part_shader = &self.part_shader;
 			self.bind_shader(part_shader);
@@ -529,9 +534,10 @@ impl InoxRenderer for OpenglRenderer {
 			part_shader.set_mvp(gl, mvp);
 
 			// frag uniforms
-			part_shader.set_opacity(gl, part.draw_state.opacity);
-			part_shader.set_mult_color(gl, part.draw_state.tint);
-			part_shader.set_screen_color(gl, part.draw_state.screen_tint);
+			let drawable_offset = &node_render_ctx.drawable_offset;
+			part_shader.set_opacity(gl, drawable_offset.clamped_opacity());
+			part_shader.set_mult_color(gl, drawable_offset.clamped_tint());
+			part_shader.set_screen_color(gl, drawable_offset.clamped_screen_tint());
 		}
 
 		unsafe {
@@ -566,7 +572,7 @@ impl InoxRenderer for OpenglRenderer {
 		}
 	}
 
-	fn finish_composite_content(&self, as_mask: bool, composite: &Composite) {
+	fn finish_composite_content(&self, as_mask: bool, node_render_ctx: &NodeRenderCtx, composite: &Composite) {
 		let gl = &self.gl;
 
 		self.clear_texture_cache();
@@ -597,9 +603,10 @@ impl InoxRenderer for OpenglRenderer {
 
 			self.set_blend_mode(comp.blend_mode);
 
-			let opacity = comp.opacity.clamp(0.0, 1.0);
-			let tint = comp.tint.clamp(Vec3::ZERO, Vec3::ONE);
-			let screen_tint = comp.screen_tint.clamp(Vec3::ZERO, Vec3::ONE);
+			let drawable_offset = &node_render_ctx.drawable_offset;
+			let opacity = drawable_offset.clamped_opacity();
+			let tint = drawable_offset.clamped_tint();
+			let screen_tint = drawable_offset.clamped_screen_tint();
 
 			let composite_shader = &self.composite_shader;
 			self.bind_shader(composite_shader);
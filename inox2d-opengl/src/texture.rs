@@ -1,6 +1,6 @@
 use glow::HasContext;
 
-use inox2d::texture::ShallowTexture;
+use inox2d::texture::{ShallowTexture, ShallowTextureFormat};
 
 #[derive(thiserror::Error, Debug)]
 #[error("Could not create texture: {0}")]
@@ -15,19 +15,57 @@ pub struct Texture {
 
 impl Texture {
 	pub fn from_shallow_texture(gl: &glow::Context, shalltex: &ShallowTexture) -> Result<Self, TextureError> {
-		Self::from_raw_pixels(gl, shalltex.pixels(), shalltex.width(), shalltex.height())
+		match shalltex.format() {
+			ShallowTextureFormat::Rgba8 => {
+				Self::from_raw_pixels(gl, shalltex.pixels(), shalltex.width(), shalltex.height())
+			}
+			ShallowTextureFormat::Bc7 => {
+				Self::from_bc7_blocks(gl, shalltex.pixels(), shalltex.width(), shalltex.height())
+			}
+		}
+	}
+
+	/// Creates a texture object with linear filtering and clamped edges, and leaves it bound.
+	unsafe fn create_bound(gl: &glow::Context) -> Result<glow::Texture, TextureError> {
+		let tex = gl.create_texture().map_err(TextureError)?;
+		gl.bind_texture(glow::TEXTURE_2D, Some(tex));
+		gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
+		gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+		gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+		gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+		Ok(tex)
+	}
+
+	/// Uploads BC7 blocks as is. The context must support BPTC texture compression.
+	pub fn from_bc7_blocks(gl: &glow::Context, blocks: &[u8], width: u32, height: u32) -> Result<Self, TextureError> {
+		let tex = unsafe { Self::create_bound(gl)? };
+		unsafe {
+			gl.compressed_tex_image_2d(
+				glow::TEXTURE_2D,
+				0,
+				glow::COMPRESSED_RGBA_BPTC_UNORM as i32,
+				width as i32,
+				height as i32,
+				0,
+				blocks.len() as i32,
+				blocks,
+			);
+			gl.bind_texture(glow::TEXTURE_2D, None);
+		}
+
+		Ok(Texture {
+			tex,
+			width,
+			height,
+			bpp: 8,
+		})
 	}
 
 	pub fn from_raw_pixels(gl: &glow::Context, pixels: &[u8], width: u32, height: u32) -> Result<Self, TextureError> {
 		let bpp = 8 * (pixels.len() / (width as usize * height as usize)) as u32;
 
-		let tex = unsafe { gl.create_texture().map_err(TextureError)? };
+		let tex = unsafe { Self::create_bound(gl)? };
 		unsafe {
-			gl.bind_texture(glow::TEXTURE_2D, Some(tex));
-			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
-			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
-			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
-			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
 			gl.tex_image_2d(
 				glow::TEXTURE_2D,
 				0,
@@ -2,7 +2,8 @@ use std::collections::HashMap;
 
 use wgpu::{util::DeviceExt, Buffer, BufferDescriptor, BufferUsages, Device};
 
-use inox2d::{node::InoxNodeUuid, puppet::Puppet};
+use inox2d::node::{data::CustomNode, InoxNodeUuid};
+use inox2d::puppet::PuppetInstance;
 
 pub struct InoxBuffers {
 	pub uniform_buffer: Buffer,
@@ -14,12 +15,16 @@ pub struct InoxBuffers {
 	pub index_buffer: Buffer,
 }
 
-pub fn buffers_for_puppet(device: &Device, puppet: &Puppet, uniform_alignment_needed: usize) -> InoxBuffers {
+pub fn buffers_for_puppet<T: CustomNode>(
+	device: &Device,
+	puppet: &PuppetInstance<T>,
+	uniform_alignment_needed: usize,
+) -> InoxBuffers {
 	let mut uniform_index_map: HashMap<InoxNodeUuid, usize> = HashMap::new();
 
 	for (i, node) in (puppet.nodes.arena.iter())
 		.map(|arena_node| arena_node.get())
-		.filter(|node| node.is_part() || node.is_composite())
+		.filter(|node| node.data.drawn_part().is_some() || node.is_composite())
 		.enumerate()
 	{
 		uniform_index_map.insert(node.uuid, i);
@@ -6,13 +6,16 @@ mod pipeline;
 
 use inox2d::math::camera::Camera;
 use inox2d::model::Model;
-use inox2d::node::data::{InoxData, MaskMode};
-use inox2d::puppet::Puppet;
+use inox2d::node::data::{CustomNode, InoxData, MaskMode};
+use inox2d::node::InoxNodeUuid;
+use inox2d::puppet::PuppetInstance;
 use inox2d::render::RenderCtxKind;
 
+use std::collections::HashMap;
+
 use encase::ShaderType;
-use glam::{vec3, Mat4, UVec2, Vec2, Vec3};
-use inox2d::texture::decode_model_textures;
+use glam::{vec3, Mat4, UVec2, Vec2};
+use inox2d::texture::{decode_model_textures, ShallowTextureFormat};
 use tracing::warn;
 use wgpu::util::TextureDataOrder;
 use wgpu::{util::DeviceExt, *};
@@ -28,7 +31,7 @@ pub struct Renderer {
 	setup: InoxPipeline,
 	model_texture_binds: Vec<BindGroup>,
 	buffers: buffers::InoxBuffers,
-	bundles: Vec<node_bundle::NodeBundle>,
+	bundles: HashMap<InoxNodeUuid, node_bundle::NodeBundle>,
 	pub camera: Camera,
 
 	viewport: UVec2,
@@ -38,7 +41,13 @@ pub struct Renderer {
 }
 
 impl Renderer {
-	pub fn new(device: &Device, queue: &Queue, texture_format: TextureFormat, model: &Model, viewport: UVec2) -> Self {
+	pub fn new<T: CustomNode>(
+		device: &Device,
+		queue: &Queue,
+		texture_format: TextureFormat,
+		model: &Model<T>,
+		viewport: UVec2,
+	) -> Self {
 		let setup = InoxPipeline::create(device, texture_format);
 
 		let mut model_texture_binds = Vec::new();
@@ -53,7 +62,8 @@ impl Renderer {
 			..SamplerDescriptor::default()
 		});
 
-		let shalltexs = decode_model_textures(model.textures.iter());
+		let support_bc7_compression = device.features().contains(Features::TEXTURE_COMPRESSION_BC);
+		let shalltexs = decode_model_textures(model.textures.iter(), support_bc7_compression);
 		for shalltex in &shalltexs {
 			let texture_size = wgpu::Extent3d {
 				width: shalltex.width(),
@@ -61,6 +71,11 @@ impl Renderer {
 				depth_or_array_layers: 1,
 			};
 
+			let format = match shalltex.format() {
+				ShallowTextureFormat::Rgba8 => wgpu::TextureFormat::Rgba8Unorm,
+				ShallowTextureFormat::Bc7 => wgpu::TextureFormat::Bc7RgbaUnorm,
+			};
+
 			let texture = device.create_texture_with_data(
 				queue,
 				&wgpu::TextureDescriptor {
@@ -68,7 +83,7 @@ impl Renderer {
 					mip_level_count: 1,
 					sample_count: 1,
 					dimension: wgpu::TextureDimension::D2,
-					format: wgpu::TextureFormat::Rgba8Unorm,
+					format,
 					usage: wgpu::TextureUsages::TEXTURE_BINDING,
 					label: Some("texture"),
 					view_formats: &[],
@@ -120,9 +135,9 @@ impl Renderer {
 	}
 
 	#[allow(clippy::too_many_arguments)]
-	fn render_part(
+	fn render_part<T: CustomNode>(
 		&self,
-		puppet: &Puppet,
+		puppet: &PuppetInstance<T>,
 
 		view: &TextureView,
 		mask_view: &TextureView,
@@ -163,7 +178,7 @@ impl Renderer {
 
 		for mask in masks {
 			let node = puppet.nodes.get_node(mask.source).unwrap();
-			let part = if let InoxData::Part(part) = &node.data {
+			let part = if let Some(part) = node.data.drawn_part() {
 				part
 			} else {
 				todo!()
@@ -207,9 +222,9 @@ impl Renderer {
 	}
 
 	#[allow(clippy::too_many_arguments)]
-	fn render_composite(
+	fn render_composite<T: CustomNode>(
 		&self,
-		puppet: &Puppet,
+		puppet: &PuppetInstance<T>,
 
 		view: &TextureView,
 		mask_view: &TextureView,
@@ -222,7 +237,16 @@ impl Renderer {
 		composite_bind: &BindGroup,
 		CompositeData(parts, uuid): &CompositeData,
 	) {
-		for (i, data) in parts.iter().enumerate() {
+		let RenderCtxKind::Composite(ref children) = puppet.render_ctx.node_render_ctxs[uuid].kind else {
+			warn!(
+				"Node {:?} is not a composite but is trying to get rendered as one",
+				uuid
+			);
+			return;
+		};
+		let parts = children.iter().filter_map(|child| parts.get(child));
+
+		for (i, data) in parts.enumerate() {
 			self.render_part(
 				puppet,
 				composite_view,
@@ -285,7 +309,13 @@ impl Renderer {
 	}
 
 	/// It is a logical error to pass in a different puppet than the one passed to create.
-	pub fn render(&mut self, queue: &Queue, device: &Device, puppet: &Puppet, view: &TextureView) {
+	pub fn render<T: CustomNode>(
+		&mut self,
+		queue: &Queue,
+		device: &Device,
+		puppet: &PuppetInstance<T>,
+		view: &TextureView,
+	) {
 		let uniform_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
 			label: Some("inox2d uniform bind group"),
 			layout: &self.setup.uniform_layout,
@@ -365,25 +395,28 @@ impl Renderer {
 		for uuid in puppet.nodes.all_node_ids() {
 			let node = puppet.nodes.get_node(uuid).unwrap();
 
-			let unif = match &node.data {
-				InoxData::Part(_) => {
+			let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
+			let drawable_offset = &node_render_ctx.drawable_offset;
+
+			let unif = match (node.data.drawn_part(), &node.data) {
+				(Some(_), _) => {
 					let mvp = Mat4::from_scale(vec3(1.0, 1.0, 0.0))
 						* self.camera.matrix(self.viewport.as_vec2())
-						* puppet.render_ctx.node_render_ctxs[&uuid].trans;
+						* node_render_ctx.trans;
 
 					Uniform {
-						opacity: 1.0,
-						mult_color: Vec3::ONE,
-						screen_color: Vec3::ZERO,
+						opacity: drawable_offset.clamped_opacity(),
+						mult_color: drawable_offset.clamped_tint(),
+						screen_color: drawable_offset.clamped_screen_tint(),
 						emission_strength: 0.0,
 						offset: Vec2::ZERO,
 						mvp,
 					}
 				}
-				InoxData::Composite(_) => Uniform {
-					opacity: 1.0,
-					mult_color: Vec3::ONE,
-					screen_color: Vec3::ZERO,
+				(None, InoxData::Composite(_)) => Uniform {
+					opacity: drawable_offset.clamped_opacity(),
+					mult_color: drawable_offset.clamped_tint(),
+					screen_color: drawable_offset.clamped_screen_tint(),
 					emission_strength: 0.0,
 					offset: Vec2::ZERO,
 					mvp: Mat4::IDENTITY,
@@ -409,7 +442,11 @@ impl Renderer {
 			label: Some("Part Render Encoder"),
 		});
 
-		for bundle in &self.bundles {
+		for uuid in &puppet.render_ctx.root_drawables_zsorted {
+			let Some(bundle) = self.bundles.get(uuid) else {
+				continue;
+			};
+
 			let op = if first {
 				first = false;
 
@@ -1,13 +1,15 @@
+use std::collections::HashMap;
+
 use encase::ShaderType;
 use tracing::warn;
 use wgpu::{BindGroup, Device, RenderBundle};
 
 use inox2d::{
 	node::{
-		data::{InoxData, Mask, Part},
+		data::{CustomNode, InoxData, Mask, Part},
 		InoxNodeUuid,
 	},
-	puppet::Puppet,
+	puppet::PuppetInstance,
 	render::RenderCtxKind,
 };
 
@@ -20,7 +22,7 @@ use super::{
 pub struct PartData(pub RenderBundle, pub Vec<Mask>);
 
 #[derive(Debug)]
-pub struct CompositeData(pub Vec<PartData>, pub InoxNodeUuid);
+pub struct CompositeData(pub HashMap<InoxNodeUuid, PartData>, pub InoxNodeUuid);
 
 #[derive(Debug)]
 pub enum NodeBundle {
@@ -29,7 +31,7 @@ pub enum NodeBundle {
 }
 
 #[allow(clippy::too_many_arguments)]
-fn part_bundle_for_part(
+fn part_bundle_for_part<T: CustomNode>(
 	device: &Device,
 	setup: &InoxPipeline,
 	buffers: &InoxBuffers,
@@ -38,7 +40,7 @@ fn part_bundle_for_part(
 
 	uuid: InoxNodeUuid,
 	part: &Part,
-	puppet: &Puppet,
+	puppet: &PuppetInstance<T>,
 ) -> PartData {
 	let mut encoder = device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
 		label: Some(&format!("part encoder: {:?}", uuid)),
@@ -83,14 +85,14 @@ fn part_bundle_for_part(
 	PartData(bundle, part.draw_state.masks.clone())
 }
 
-pub fn node_bundles_for_model(
+pub fn node_bundles_for_model<T: CustomNode>(
 	device: &Device,
 	setup: &InoxPipeline,
 	buffers: &InoxBuffers,
 	model_texture_binds: &[BindGroup],
 
-	puppet: &Puppet,
-) -> Vec<NodeBundle> {
+	puppet: &PuppetInstance<T>,
+) -> HashMap<InoxNodeUuid, NodeBundle> {
 	let uniform_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
 		label: Some("inox2d uniform bind group"),
 		layout: &setup.uniform_layout,
@@ -104,13 +106,14 @@ pub fn node_bundles_for_model(
 		}],
 	});
 
-	let mut out = Vec::new();
+	// Bundles are drawn in the order of the render context, which changes with zsort bindings
+	let mut out = HashMap::new();
 
 	for uuid in puppet.nodes.zsorted_root() {
 		let node = puppet.nodes.get_node(uuid).unwrap();
 
-		if let InoxData::Part(part) = &node.data {
-			out.push(NodeBundle::Part(part_bundle_for_part(
+		if let Some(part) = node.data.drawn_part() {
+			let bundle = part_bundle_for_part(
 				device,
 				setup,
 				buffers,
@@ -119,7 +122,8 @@ pub fn node_bundles_for_model(
 				uuid,
 				part,
 				puppet,
-			)));
+			);
+			out.insert(uuid, NodeBundle::Part(bundle));
 		} else if let InoxData::Composite(_) = &node.data {
 			let mut encoder = device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
 				label: Some(&format!("composite children encoder: {:?}", uuid)),
@@ -138,12 +142,12 @@ pub fn node_bundles_for_model(
 			encoder.set_vertex_buffer(2, buffers.deform_buffer.slice(..));
 			encoder.set_index_buffer(buffers.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
-			let mut bundles = Vec::new();
+			let mut bundles = HashMap::new();
 
 			for child_id in puppet.nodes.zsorted_children(uuid) {
 				let child = puppet.nodes.get_node(child_id).unwrap();
 
-				if let InoxData::Part(part) = &child.data {
+				if let Some(part) = child.data.drawn_part() {
 					let bundle = part_bundle_for_part(
 						device,
 						setup,
@@ -154,11 +158,11 @@ pub fn node_bundles_for_model(
 						part,
 						puppet,
 					);
-					bundles.push(bundle);
+					bundles.insert(child_id, bundle);
 				}
 			}
 
-			out.push(NodeBundle::Composite(CompositeData(bundles, uuid)));
+			out.insert(uuid, NodeBundle::Composite(CompositeData(bundles, uuid)));
 		}
 	}
 
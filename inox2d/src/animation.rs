@@ -0,0 +1,452 @@
+//! Keyframed animations of params, such as idle loops or gestures.
+//!
+//! An animation is made of lanes, each animating one axis of a param with keyframes.
+//! An [`AnimationPlayer`] samples the animations it plays and pushes the resulting values to the params of a puppet.
+
+use std::collections::HashMap;
+
+use glam::Vec2;
+
+use crate::math::easing::{Easing, Fade};
+use crate::math::interp::{interpolate_span, InterpolateMode};
+use crate::params::{ParamMergeMode, ParamUuid};
+use crate::puppet::Puppet;
+
+/// Source of the values pushed by an [`AnimationPlayer`].
+pub const ANIMATION_PARAM_SOURCE: &str = "animation";
+/// Source of the offsets pushed by an [`AnimationPlayer`] for additive animations and lanes.
+pub const ADDITIVE_ANIMATION_PARAM_SOURCE: &str = "animation.additive";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keyframe {
+	pub frame: i32,
+	pub value: f32,
+	/// Tension of the curve around the keyframe. Kept for round trips, not used when sampling.
+	pub tension: f32,
+}
+
+/// Keyframes animating one axis of a param.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationLane {
+	pub param: ParamUuid,
+	/// Animated axis of the param, 0 for x and 1 for y.
+	pub target: usize,
+	pub interpolation: InterpolateMode,
+	/// Additive lanes offset the value of the param from its default, other lanes replace it.
+	pub merge_mode: ParamMergeMode,
+	/// Keyframes, sorted by frame.
+	pub keyframes: Vec<Keyframe>,
+}
+
+impl AnimationLane {
+	/// Value of the lane at a frame, holding the first and last keyframes outside of their range.
+	pub fn sample(&self, frame: f32) -> Option<f32> {
+		let keyframes = &self.keyframes;
+		let next = keyframes.partition_point(|keyframe| keyframe.frame as f32 <= frame);
+		if next == 0 {
+			return keyframes.first().map(|keyframe| keyframe.value);
+		}
+		if next == keyframes.len() {
+			return keyframes.last().map(|keyframe| keyframe.value);
+		}
+
+		let indices = [
+			next.saturating_sub(2),
+			next - 1,
+			next,
+			(next + 1).min(keyframes.len() - 1),
+		];
+		Some(interpolate_span(
+			frame,
+			indices.map(|i| keyframes[i].frame as f32),
+			indices.map(|i| keyframes[i].value),
+			self.interpolation,
+		))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Animation {
+	pub name: String,
+	/// Duration of a frame, in seconds.
+	pub timestep: f32,
+	/// Whether all lanes offset the values of params from their defaults, instead of replacing them.
+	pub additive: bool,
+	/// Weight of the animation when blended with other sources.
+	pub weight: f32,
+	/// Length of the animation, in frames.
+	pub length: i32,
+	/// Frame at which looping playback starts over. Negative for the start of the animation.
+	pub lead_in: i32,
+	/// Frame at which looping playback ends. Negative for the end of the animation.
+	pub lead_out: i32,
+	pub lanes: Vec<AnimationLane>,
+}
+
+impl Animation {
+	/// Duration of the animation, in seconds.
+	pub fn duration(&self) -> f32 {
+		self.length as f32 * self.timestep
+	}
+
+	/// First and last frame of the looping part of the animation.
+	pub fn loop_range(&self) -> (f32, f32) {
+		let start = if (0..self.length).contains(&self.lead_in) {
+			self.lead_in
+		} else {
+			0
+		};
+		let end = if self.lead_out > start && self.lead_out <= self.length {
+			self.lead_out
+		} else {
+			self.length
+		};
+		(start as f32, end as f32)
+	}
+
+	/// Frame reached after playing the animation for `time` seconds.
+	///
+	/// Looping animations play their lead-in once, then repeat their loop range.
+	pub fn frame_at(&self, time: f32, looping: bool) -> f32 {
+		let frame = (time / self.timestep).max(0.0);
+		let (start, end) = self.loop_range();
+		if looping && frame >= end && end > start {
+			start + (frame - start) % (end - start)
+		} else {
+			frame.min(self.length as f32)
+		}
+	}
+}
+
+impl<T> Puppet<T> {
+	pub fn get_animation(&self, name: &str) -> Option<&Animation> {
+		self.animations.iter().find(|animation| animation.name == name)
+	}
+}
+
+#[derive(Debug, Clone)]
+struct PlayingClip {
+	name: String,
+	/// Playback time, in seconds.
+	time: f32,
+	speed: f32,
+	looping: bool,
+	weight: f32,
+	fade: Option<Fade>,
+}
+
+/// Values of the lanes animating a param, summed over the playing clips.
+#[derive(Default)]
+struct ParamSamples {
+	blended: Vec2,
+	weights: Vec2,
+	added: Vec2,
+}
+
+/// Plays the animations of a puppet, blending between them while crossfading.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationPlayer {
+	clips: Vec<PlayingClip>,
+}
+
+impl AnimationPlayer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Play an animation from the start, stopping all others.
+	pub fn play(&mut self, name: &str, looping: bool) {
+		self.clips.clear();
+		self.clips.push(PlayingClip {
+			name: name.to_owned(),
+			time: 0.0,
+			speed: 1.0,
+			looping,
+			weight: 1.0,
+			fade: None,
+		});
+	}
+
+	/// Fade an animation in over `duration` seconds, and all others out.
+	///
+	/// An animation that is already playing keeps its playback time.
+	pub fn crossfade(&mut self, name: &str, looping: bool, duration: f32, easing: Easing) {
+		for clip in self.clips.iter_mut().filter(|clip| clip.name != name) {
+			clip.fade = Some(Fade::new(clip.weight, 0.0, duration, easing));
+		}
+
+		let clip = match self.clips.iter_mut().find(|clip| clip.name == name) {
+			Some(clip) => clip,
+			None => {
+				self.clips.push(PlayingClip {
+					name: name.to_owned(),
+					time: 0.0,
+					speed: 1.0,
+					looping,
+					weight: 0.0,
+					fade: None,
+				});
+				self.clips.last_mut().unwrap()
+			}
+		};
+		clip.looping = looping;
+		clip.fade = Some(Fade::new(clip.weight, 1.0, duration, easing));
+
+		if duration <= 0.0 {
+			self.update(0.0);
+		}
+	}
+
+	/// Fade an animation out over `duration` seconds.
+	pub fn stop(&mut self, name: &str, duration: f32, easing: Easing) {
+		for clip in self.clips.iter_mut().filter(|clip| clip.name == name) {
+			clip.fade = Some(Fade::new(clip.weight, 0.0, duration, easing));
+		}
+		if duration <= 0.0 {
+			self.update(0.0);
+		}
+	}
+
+	pub fn stop_all(&mut self) {
+		self.clips.clear();
+	}
+
+	/// Set the playback speed of an animation, 1 being its normal speed.
+	pub fn set_speed(&mut self, name: &str, speed: f32) {
+		for clip in self.clips.iter_mut().filter(|clip| clip.name == name) {
+			clip.speed = speed;
+		}
+	}
+
+	pub fn is_playing(&self, name: &str) -> bool {
+		self.clips.iter().any(|clip| clip.name == name)
+	}
+
+	/// Playback time of an animation in seconds, if it is playing.
+	pub fn time(&self, name: &str) -> Option<f32> {
+		self.clips.iter().find(|clip| clip.name == name).map(|clip| clip.time)
+	}
+
+	/// Current weight of an animation, 0 if it is not playing.
+	pub fn weight(&self, name: &str) -> f32 {
+		self.clips
+			.iter()
+			.find(|clip| clip.name == name)
+			.map_or(0.0, |clip| clip.weight)
+	}
+
+	/// Advance playback and fades by `dt` seconds.
+	pub fn update(&mut self, dt: f32) {
+		for clip in &mut self.clips {
+			clip.time += dt * clip.speed;
+
+			let Some(ref mut fade) = clip.fade else {
+				continue;
+			};
+			let (weight, done) = fade.advance(dt);
+			clip.weight = weight;
+			if done {
+				clip.fade = None;
+			}
+		}
+	}
+
+	/// Sample the playing animations of a puppet and push the blended values to its params.
+	///
+	/// Lanes replacing values are blended by the weights of their animations, then with the other sources of the
+	/// puppet if these weights add up to less than 1. Additive lanes are added on top.
+	/// The axis of a param without any lane is left to the other sources.
+	///
+	/// Animations that ended or faded out completely stop being pushed after this call.
+	pub fn apply<T>(&mut self, puppet: &mut Puppet<T>) {
+		let mut samples = HashMap::<ParamUuid, ParamSamples>::new();
+		for clip in &self.clips {
+			let Some(animation) = puppet.get_animation(&clip.name) else {
+				continue;
+			};
+
+			let frame = animation.frame_at(clip.time, clip.looping);
+			let weight = clip.weight * animation.weight;
+			for lane in animation.lanes.iter().filter(|lane| lane.target < 2) {
+				let (Some(param), Some(value)) = (puppet.get_param(lane.param), lane.sample(frame)) else {
+					continue;
+				};
+
+				let sample = samples.entry(lane.param).or_default();
+				if animation.additive || lane.merge_mode == ParamMergeMode::Additive {
+					sample.added[lane.target] += (value - param.defaults[lane.target]) * weight;
+				} else {
+					sample.blended[lane.target] += value * weight;
+					sample.weights[lane.target] += weight;
+				}
+			}
+		}
+
+		for (uuid, sample) in samples {
+			if sample.weights != Vec2::ZERO {
+				// Each axis is pushed with its own weight, so that an axis without lanes gets no contribution
+				let animated = sample.weights.cmpgt(Vec2::ZERO);
+				let value = Vec2::select(animated, sample.blended / sample.weights, Vec2::ZERO);
+				let _ = puppet.push_param_axes(
+					ANIMATION_PARAM_SOURCE,
+					uuid,
+					value,
+					ParamMergeMode::Weighted,
+					sample.weights,
+				);
+			}
+			if sample.added != Vec2::ZERO {
+				let _ = puppet.push_param(
+					ADDITIVE_ANIMATION_PARAM_SOURCE,
+					uuid,
+					sample.added,
+					ParamMergeMode::Additive,
+					1.0,
+				);
+			}
+		}
+
+		self.clips.retain(|clip| {
+			let ended = puppet
+				.get_animation(&clip.name)
+				.is_some_and(|animation| !clip.looping && clip.time >= animation.duration());
+			let faded_out = clip.weight == 0.0 && clip.fade.is_none();
+			!ended && !faded_out
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::vec2;
+
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+
+	const YAW_PITCH: ParamUuid = ParamUuid(10);
+
+	fn lane(target: usize, keyframes: &[(i32, f32)]) -> AnimationLane {
+		AnimationLane {
+			param: YAW_PITCH,
+			target,
+			interpolation: InterpolateMode::Linear,
+			merge_mode: ParamMergeMode::Forced,
+			keyframes: (keyframes.iter())
+				.map(|&(frame, value)| Keyframe {
+					frame,
+					value,
+					tension: 0.5,
+				})
+				.collect(),
+		}
+	}
+
+	fn animation(name: &str, lanes: Vec<AnimationLane>) -> Animation {
+		Animation {
+			name: name.to_owned(),
+			timestep: 0.1,
+			additive: false,
+			weight: 1.0,
+			length: 10,
+			lead_in: -1,
+			lead_out: -1,
+			lanes,
+		}
+	}
+
+	fn test_puppet_with_animations() -> Puppet {
+		let mut puppet = test_puppet();
+		puppet.data_mut().animations = vec![
+			animation("sway", vec![lane(0, &[(0, 0.0), (10, 1.0)])]),
+			animation("nod", vec![lane(1, &[(0, 1.0)])]),
+		];
+		puppet
+	}
+
+	#[test]
+	fn test_sample_lane() {
+		let linear = lane(0, &[(2, 1.0), (4, 0.0), (8, 1.0)]);
+		assert_eq!(linear.sample(0.0), Some(1.0));
+		assert_eq!(linear.sample(3.0), Some(0.5));
+		assert_eq!(linear.sample(7.0), Some(0.75));
+		assert_eq!(linear.sample(9.0), Some(1.0));
+		assert_eq!(lane(0, &[]).sample(1.0), None);
+	}
+
+	#[test]
+	fn test_loop_range() {
+		let mut animation = animation("loop", Vec::new());
+		assert_eq!(animation.frame_at(0.5, true), 5.0);
+		assert_eq!(animation.frame_at(1.5, false), 10.0);
+		assert!((animation.frame_at(1.5, true) - 5.0).abs() < 1e-4);
+
+		animation.lead_in = 4;
+		animation.lead_out = 8;
+		assert_eq!(animation.loop_range(), (4.0, 8.0));
+		assert!((animation.frame_at(0.9, true) - 5.0).abs() < 1e-4);
+	}
+
+	#[test]
+	fn test_playback() {
+		let mut puppet = test_puppet_with_animations();
+		let mut player = AnimationPlayer::new();
+		player.play("sway", false);
+		player.set_speed("sway", 2.0);
+		player.update(0.25);
+		player.apply(&mut puppet);
+		puppet.end_set_params(0.0);
+		assert_eq!(player.time("sway"), Some(0.5));
+		assert_eq!(puppet.param_value(YAW_PITCH), Some(vec2(0.5, 0.0)));
+
+		// The last frame is pushed once before the animation ends
+		player.update(0.5);
+		player.apply(&mut puppet);
+		puppet.end_set_params(0.0);
+		assert_eq!(puppet.param_value(YAW_PITCH), Some(vec2(1.0, 0.0)));
+		assert!(!player.is_playing("sway"));
+	}
+
+	#[test]
+	fn test_crossfade() {
+		let mut puppet = test_puppet_with_animations();
+		let mut player = AnimationPlayer::new();
+		player.play("sway", true);
+		player.crossfade("nod", true, 1.0, Easing::Linear);
+		player.update(0.5);
+		assert_eq!(player.weight("sway"), 0.5);
+		assert_eq!(player.weight("nod"), 0.5);
+
+		// Both clips are half faded in, and blended with the tracked value
+		puppet.set_param(YAW_PITCH, vec2(0.0, 0.0)).unwrap();
+		player.apply(&mut puppet);
+		puppet.end_set_params(0.0);
+		let value = puppet.param_value(YAW_PITCH).unwrap();
+		assert!((value - vec2(0.25, 0.5)).length() < 1e-4, "{value}");
+
+		player.update(0.5);
+		player.apply(&mut puppet);
+		assert!(!player.is_playing("sway"));
+		assert!(player.is_playing("nod"));
+	}
+
+	#[test]
+	fn test_unanimated_axis_follows_other_sources() {
+		let mut puppet = test_puppet_with_animations();
+		let mut player = AnimationPlayer::new();
+		player.play("sway", true);
+
+		// Only the x axis is animated at full weight, the tracked y axis must not lag or freeze
+		for (i, y) in [0.2, 0.7, -0.4].into_iter().enumerate() {
+			puppet.set_param(YAW_PITCH, vec2(0.0, y)).unwrap();
+			player.update(0.1);
+			player.apply(&mut puppet);
+			puppet.end_set_params(0.0);
+
+			let value = puppet.param_value(YAW_PITCH).unwrap();
+			assert!((value - vec2(0.1 * (i + 1) as f32, y)).length() < 1e-4, "{value}");
+		}
+	}
+}
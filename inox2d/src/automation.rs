@@ -0,0 +1,353 @@
+//! Automation of params by generators, such as breathing, idle sway and auto-blink.
+//!
+//! Automations are stored in the puppet, and evaluated at the start of [`Puppet::end_set_params`], before physics.
+//! Model files keep them in their vendor data, the `automation` key of Inochi2D payloads is left alone.
+//! Each generator outputs a value from 0 to 1, mapped to the range of each param axis it is bound to.
+
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use glam::Vec2;
+
+use crate::params::{ParamMergeMode, ParamUuid};
+use crate::puppet::Puppet;
+
+/// Name of the vendor data holding the automations of a model.
+pub const AUTOMATIONS_VENDOR_NAME: &str = "com.inox2d.automations";
+/// Source of the values pushed by automations.
+pub const AUTOMATION_PARAM_SOURCE: &str = "automation";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WaveShape {
+	#[default]
+	Sine,
+	Triangle,
+	Square,
+	Sawtooth,
+}
+
+impl WaveShape {
+	/// Value of the wave from 0 to 1, at a position in its period from 0 to 1.
+	fn eval(self, x: f32) -> f32 {
+		match self {
+			WaveShape::Sine => 0.5 - 0.5 * (x * TAU).cos(),
+			WaveShape::Triangle => 1.0 - (2.0 * x - 1.0).abs(),
+			WaveShape::Square => {
+				if x < 0.5 {
+					0.0
+				} else {
+					1.0
+				}
+			}
+			WaveShape::Sawtooth => x,
+		}
+	}
+}
+
+/// Generator of the value of an automation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Generator {
+	/// Periodic wave, starting at 0 at time 0 when not shifted.
+	Wave {
+		shape: WaveShape,
+		/// Duration of a period, in seconds.
+		period: f32,
+		/// Shift of the wave, as a fraction of its period.
+		phase: f32,
+	},
+	/// Random values, smoothly interpolated.
+	Noise {
+		/// Number of random values reached per second.
+		frequency: f32,
+	},
+	/// 1 most of the time, going down to 0 and back up at random intervals.
+	Blink {
+		/// Average time between blinks, in seconds.
+		interval: f32,
+		/// Variation of the time between blinks, as a fraction of the interval from 0 up to 1, excluding 1.
+		variance: f32,
+		/// Duration of a blink, in seconds.
+		duration: f32,
+	},
+}
+
+impl Generator {
+	/// Slow sine wave, for breathing.
+	pub fn breathing() -> Self {
+		Generator::Wave {
+			shape: WaveShape::Sine,
+			period: 4.0,
+			phase: 0.0,
+		}
+	}
+
+	/// Blinks every 4 seconds on average.
+	pub fn blink() -> Self {
+		Generator::Blink {
+			interval: 4.0,
+			variance: 0.5,
+			duration: 0.2,
+		}
+	}
+}
+
+/// Param axis driven by an automation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutomationBinding {
+	pub param: ParamUuid,
+	/// Driven axis of the param, 0 for x and 1 for y.
+	pub axis: usize,
+	/// Values of the param axis for outputs 0 and 1 of the generator.
+	pub range: Vec2,
+}
+
+/// Small xorshift generator, so that automations are reproducible without extra dependencies.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Rng(u64);
+
+impl Rng {
+	fn seeded(name: &str) -> Self {
+		// FNV-1a hash of the name, made odd since xorshift gets stuck at 0
+		let hash = (name.bytes()).fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+			(hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+		});
+		Self(hash | 1)
+	}
+
+	/// Random value from 0 to 1.
+	fn next(&mut self) -> f32 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		(self.0 >> 40) as f32 / (1_u64 << 24) as f32
+	}
+}
+
+/// Runtime state of an automation, specific to each instance of a puppet.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutomationState {
+	time: f32,
+	rng: Option<Rng>,
+	/// Noise values being interpolated between.
+	noise: (f32, f32),
+	/// Time at which the next blink starts.
+	next_blink: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Automation {
+	pub name: String,
+	pub generator: Generator,
+	pub bindings: Vec<AutomationBinding>,
+}
+
+impl Automation {
+	pub fn new(name: impl Into<String>, generator: Generator, bindings: Vec<AutomationBinding>) -> Self {
+		Self {
+			name: name.into(),
+			generator,
+			bindings,
+		}
+	}
+
+	/// Advance the generator by `dt` seconds, returning its new output from 0 to 1.
+	pub fn update(&self, state: &mut AutomationState, dt: f32) -> f32 {
+		let first_update = state.rng.is_none();
+		let rng = state.rng.get_or_insert_with(|| Rng::seeded(&self.name));
+		let previous_time = state.time;
+		state.time += dt;
+
+		match self.generator {
+			Generator::Wave { shape, period, phase } => {
+				if period <= 0.0 {
+					return 0.0;
+				}
+				shape.eval((state.time / period + phase).rem_euclid(1.0))
+			}
+			Generator::Noise { frequency } => {
+				if first_update {
+					state.noise = (rng.next(), rng.next());
+				}
+				let steps = (state.time * frequency).floor() - (previous_time * frequency).floor();
+				for _ in 0..(steps as u32).min(2) {
+					state.noise = (state.noise.1, rng.next());
+				}
+
+				let s = (state.time * frequency).fract();
+				let (beg, end) = state.noise;
+				beg + (end - beg) * s * s * (3.0 - 2.0 * s)
+			}
+			Generator::Blink {
+				interval,
+				variance,
+				duration,
+			} => {
+				let next_interval = |rng: &mut Rng| interval * (1.0 + variance * (2.0 * rng.next() - 1.0));
+				if first_update {
+					state.next_blink = next_interval(rng);
+				}
+				// Schedule the next blink once the current one is over, skipping the ones a long step went past
+				let blink_end = state.next_blink + duration;
+				if state.time >= blink_end {
+					state.next_blink = blink_end.max(state.time - interval) + next_interval(rng).max(0.0);
+				}
+
+				let progress = (state.time - state.next_blink) / duration;
+				if (0.0..1.0).contains(&progress) {
+					(2.0 * progress - 1.0).abs()
+				} else {
+					1.0
+				}
+			}
+		}
+	}
+}
+
+impl<T> Puppet<T> {
+	/// Advance all automations by `dt` seconds and push their values to the params they are bound to.
+	pub(crate) fn update_automations(&mut self, dt: f32) {
+		if self.automations.is_empty() {
+			return;
+		}
+
+		let data = Arc::clone(self.data());
+		let mut states = std::mem::take(&mut self.automation_states);
+		states.resize_with(data.automations.len(), AutomationState::default);
+
+		let mut values = HashMap::<ParamUuid, Vec2>::new();
+		for (automation, state) in data.automations.iter().zip(&mut states) {
+			let output = automation.update(state, dt);
+			for binding in automation.bindings.iter().filter(|binding| binding.axis < 2) {
+				// The other axis keeps the value pushed by other sources
+				let Some(current) = self.pending_param_value(binding.param) else {
+					continue;
+				};
+				let value = values.entry(binding.param).or_insert(current);
+				value[binding.axis] = binding.range.x + (binding.range.y - binding.range.x) * output;
+			}
+		}
+		self.automation_states = states;
+
+		for (uuid, value) in values {
+			let _ = self.push_param(AUTOMATION_PARAM_SOURCE, uuid, value, ParamMergeMode::Forced, 1.0);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::vec2;
+
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+
+	fn assert_near(actual: f32, expected: f32) {
+		assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+	}
+
+	#[test]
+	fn test_waves() {
+		let wave = |shape, phase| {
+			Automation::new(
+				"wave",
+				Generator::Wave {
+					shape,
+					period: 2.0,
+					phase,
+				},
+				Vec::new(),
+			)
+		};
+
+		let (sine, mut state) = (wave(WaveShape::Sine, 0.0), AutomationState::default());
+		assert_near(sine.update(&mut state, 0.5), 0.5);
+		assert_near(sine.update(&mut state, 0.5), 1.0);
+		assert_near(sine.update(&mut state, 1.0), 0.0);
+
+		let (triangle, mut state) = (wave(WaveShape::Triangle, 0.25), AutomationState::default());
+		assert_near(triangle.update(&mut state, 0.0), 0.5);
+		assert_near(triangle.update(&mut state, 0.5), 1.0);
+
+		let (sawtooth, mut state) = (wave(WaveShape::Sawtooth, 0.0), AutomationState::default());
+		assert_near(sawtooth.update(&mut state, 2.5), 0.25);
+	}
+
+	#[test]
+	fn test_noise() {
+		let noise = Automation::new("noise", Generator::Noise { frequency: 2.0 }, Vec::new());
+		let mut state = AutomationState::default();
+		let mut previous = noise.update(&mut state, 0.0);
+		for _ in 0..100 {
+			let value = noise.update(&mut state, 0.01);
+			assert!((0.0..=1.0).contains(&value));
+			assert!((value - previous).abs() < 0.1);
+			previous = value;
+		}
+
+		// Reproducible for the same name
+		let mut other_state = AutomationState::default();
+		noise.update(&mut other_state, 0.0);
+		assert_eq!(noise.update(&mut other_state, 1.0), previous);
+	}
+
+	#[test]
+	fn test_blink() {
+		let blink = Automation::new("blink", Generator::blink(), Vec::new());
+		let mut state = AutomationState::default();
+		let outputs = (0..2000).map(|_| blink.update(&mut state, 0.01)).collect::<Vec<_>>();
+		assert!(outputs.iter().all(|value| (0.0..=1.0).contains(value)));
+
+		// Eyes stay closed for at most 1 frame per blink, and there are several blinks in 20 seconds
+		let closings = (outputs.windows(2))
+			.filter(|pair| pair[0] >= 0.5 && pair[1] < 0.5)
+			.count();
+		assert!((2..=10).contains(&closings), "{closings} blinks");
+		assert_eq!(outputs[0], 1.0);
+
+		// Degenerate blinks do not hang, even over long steps
+		let instant = Automation::new(
+			"instant",
+			Generator::Blink {
+				interval: 0.0,
+				variance: 1.0,
+				duration: 0.0,
+			},
+			Vec::new(),
+		);
+		let mut state = AutomationState::default();
+		for dt in [0.01, 1e6, 0.01] {
+			assert_eq!(instant.update(&mut state, dt), 1.0);
+		}
+		let mut state = AutomationState::default();
+		assert!((0.0..=1.0).contains(&blink.update(&mut state, 1e9)));
+	}
+
+	#[test]
+	fn test_puppet_automation() {
+		let mut puppet = test_puppet();
+		puppet.data_mut().automations.push(Automation::new(
+			"breathing",
+			Generator::breathing(),
+			vec![AutomationBinding {
+				param: ParamUuid(10),
+				axis: 1,
+				range: vec2(-1.0, 1.0),
+			}],
+		));
+
+		puppet.set_param(ParamUuid(10), vec2(0.5, 0.5)).unwrap();
+		puppet.end_set_params(1.0);
+		assert_near(puppet.param_value(ParamUuid(10)).unwrap().y, 0.0);
+		assert_eq!(puppet.param_value(ParamUuid(10)).unwrap().x, 0.5);
+
+		puppet.end_set_params(1.0);
+		assert_near(puppet.param_value(ParamUuid(10)).unwrap().y, 1.0);
+	}
+}
@@ -0,0 +1,338 @@
+//! Expression presets: named snapshots of param values, such as "happy" or "blush".
+//!
+//! Presets are stored in the vendor data of a model, and faded in and out by an [`ExpressionPlayer`]
+//! as offsets from the defaults of params, which add up with the values pushed by other sources.
+
+use std::collections::HashMap;
+
+use glam::Vec2;
+use json::JsonValue;
+
+use crate::formats::{JsonError, JsonObject};
+use crate::math::easing::{Easing, Fade};
+use crate::model::VendorData;
+use crate::params::{ParamMergeMode, ParamUuid};
+use crate::puppet::Puppet;
+
+/// Name of the vendor data holding the presets of a model.
+pub const EXPRESSIONS_VENDOR_NAME: &str = "com.inox2d.expressions";
+/// Source of the values pushed by an [`ExpressionPlayer`].
+pub const EXPRESSION_PARAM_SOURCE: &str = "expression";
+
+/// Named snapshot of param values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+	pub name: String,
+	pub values: Vec<(ParamUuid, Vec2)>,
+}
+
+impl Expression {
+	/// Snapshot of the current values of the given params. Unknown params are skipped.
+	pub fn capture<T>(
+		puppet: &Puppet<T>,
+		name: impl Into<String>,
+		params: impl IntoIterator<Item = ParamUuid>,
+	) -> Self {
+		Self {
+			name: name.into(),
+			values: (params.into_iter())
+				.filter_map(|uuid| Some((uuid, puppet.param_value(uuid)?)))
+				.collect(),
+		}
+	}
+
+	/// Snapshot of the current values of the params that are not at their default.
+	pub fn capture_changed<T>(puppet: &Puppet<T>, name: impl Into<String>) -> Self {
+		let changed = (puppet.params())
+			.filter(|param| puppet.param_value(param.uuid) != Some(param.defaults))
+			.map(|param| param.uuid)
+			.collect::<Vec<_>>();
+		Self::capture(puppet, name, changed)
+	}
+
+	fn from_json(obj: &JsonObject) -> Result<Self, JsonError> {
+		let mut values = Vec::new();
+		for (i, value) in obj.get_list("values")?.iter().enumerate() {
+			let JsonValue::Object(value) = value else {
+				return Err(JsonError::ElementIsNotObject.in_list(i).nested("values"));
+			};
+			let value = JsonObject(value);
+			let parsed = (|| Ok((ParamUuid(value.get_u32("param")?), value.get_vec2("value")?)))();
+			values.push(parsed.map_err(|e: JsonError| e.in_list(i).nested("values"))?);
+		}
+
+		Ok(Self {
+			name: obj.get_str("name")?.to_owned(),
+			values,
+		})
+	}
+
+	fn to_json(&self) -> JsonValue {
+		let values = (self.values.iter())
+			.map(|(uuid, value)| json::object! { "param": uuid.0, "value": [value.x, value.y] })
+			.collect::<Vec<_>>();
+
+		json::object! {
+			"name": self.name.as_str(),
+			"values": values,
+		}
+	}
+}
+
+/// Expression presets of a model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExpressionSet {
+	pub expressions: Vec<Expression>,
+}
+
+impl ExpressionSet {
+	pub fn get(&self, name: &str) -> Option<&Expression> {
+		self.expressions.iter().find(|expression| expression.name == name)
+	}
+
+	/// Add an expression, replacing any previous one of the same name.
+	pub fn insert(&mut self, expression: Expression) {
+		match self.expressions.iter_mut().find(|e| e.name == expression.name) {
+			Some(previous) => *previous = expression,
+			None => self.expressions.push(expression),
+		}
+	}
+
+	/// Read the presets stored in the vendor data of a model, if any.
+	pub fn from_vendor_data(vendors: &[VendorData]) -> Result<Self, JsonError> {
+		let Some(vendor) = vendors.iter().find(|vendor| vendor.name == EXPRESSIONS_VENDOR_NAME) else {
+			return Ok(Self::default());
+		};
+
+		let JsonValue::Array(ref list) = vendor.payload else {
+			return Err(JsonError::ValueIsNotList(EXPRESSIONS_VENDOR_NAME.to_owned()));
+		};
+
+		let mut expressions = Vec::with_capacity(list.len());
+		for (i, expression) in list.iter().enumerate() {
+			let JsonValue::Object(expression) = expression else {
+				return Err(JsonError::ElementIsNotObject.in_list(i));
+			};
+			expressions.push(Expression::from_json(&JsonObject(expression)).map_err(|e| e.in_list(i))?);
+		}
+		Ok(Self { expressions })
+	}
+
+	pub fn to_vendor_data(&self) -> VendorData {
+		VendorData {
+			name: EXPRESSIONS_VENDOR_NAME.to_owned(),
+			payload: JsonValue::Array(self.expressions.iter().map(Expression::to_json).collect()),
+		}
+	}
+
+	/// Write the presets into the vendor data of a model, replacing the ones already there.
+	pub fn store(&self, vendors: &mut Vec<VendorData>) {
+		let vendor = self.to_vendor_data();
+		match vendors.iter_mut().find(|v| v.name == EXPRESSIONS_VENDOR_NAME) {
+			Some(previous) => *previous = vendor,
+			None => vendors.push(vendor),
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+struct ExpressionLayer {
+	name: String,
+	weight: f32,
+	fade: Option<Fade>,
+}
+
+/// Fades expressions in and out over time, and layers them over the other sources of a puppet.
+#[derive(Clone, Debug, Default)]
+pub struct ExpressionPlayer {
+	layers: Vec<ExpressionLayer>,
+}
+
+impl ExpressionPlayer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Current weight of an expression, 0 if it is not playing.
+	pub fn weight(&self, name: &str) -> f32 {
+		self.layers
+			.iter()
+			.find(|layer| layer.name == name)
+			.map_or(0.0, |layer| layer.weight)
+	}
+
+	/// Fade an expression from its current weight to `weight` over `duration` seconds.
+	pub fn fade_to(&mut self, name: &str, weight: f32, duration: f32, easing: Easing) {
+		let index = match self.layers.iter().position(|layer| layer.name == name) {
+			Some(index) => index,
+			None => {
+				self.layers.push(ExpressionLayer {
+					name: name.to_owned(),
+					weight: 0.0,
+					fade: None,
+				});
+				self.layers.len() - 1
+			}
+		};
+
+		let layer = &mut self.layers[index];
+		if duration > 0.0 {
+			layer.fade = Some(Fade::new(layer.weight, weight, duration, easing));
+		} else {
+			layer.weight = weight;
+			layer.fade = None;
+		}
+	}
+
+	/// Fade an expression in, and all the others out.
+	pub fn crossfade(&mut self, name: &str, duration: f32, easing: Easing) {
+		let others = (self.layers.iter())
+			.filter(|layer| layer.name != name)
+			.map(|layer| layer.name.clone())
+			.collect::<Vec<_>>();
+		for other in others {
+			self.fade_to(&other, 0.0, duration, easing);
+		}
+		self.fade_to(name, 1.0, duration, easing);
+	}
+
+	/// Fade all expressions out.
+	pub fn fade_out_all(&mut self, duration: f32, easing: Easing) {
+		let names = self.layers.iter().map(|layer| layer.name.clone()).collect::<Vec<_>>();
+		for name in names {
+			self.fade_to(&name, 0.0, duration, easing);
+		}
+	}
+
+	/// Advance the fades by `dt` seconds.
+	pub fn update(&mut self, dt: f32) {
+		for layer in &mut self.layers {
+			let Some(ref mut fade) = layer.fade else {
+				continue;
+			};
+
+			let (weight, done) = fade.advance(dt);
+			layer.weight = weight;
+			if done {
+				layer.fade = None;
+			}
+		}
+	}
+
+	/// Push the weighted offsets of the playing expressions to the params of a puppet,
+	/// to be added to the values of other sources such as tracking.
+	///
+	/// Expressions missing from `expressions` are ignored.
+	/// Expressions faded out completely stop being pushed after this call.
+	pub fn apply<T>(&mut self, expressions: &ExpressionSet, puppet: &mut Puppet<T>) {
+		let mut offsets = HashMap::new();
+		for layer in &self.layers {
+			let Some(expression) = expressions.get(&layer.name) else {
+				continue;
+			};
+
+			for &(uuid, value) in &expression.values {
+				let Some(param) = puppet.get_param(uuid) else {
+					continue;
+				};
+				*offsets.entry(uuid).or_insert(Vec2::ZERO) += (value - param.defaults) * layer.weight;
+			}
+		}
+
+		for (uuid, offset) in offsets {
+			let _ = puppet.push_param(EXPRESSION_PARAM_SOURCE, uuid, offset, ParamMergeMode::Additive, 1.0);
+		}
+
+		self.layers.retain(|layer| layer.weight != 0.0 || layer.fade.is_some());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::vec2;
+
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+
+	const YAW_PITCH: ParamUuid = ParamUuid(10);
+
+	fn test_expressions() -> ExpressionSet {
+		let mut expressions = ExpressionSet::default();
+		expressions.insert(Expression {
+			name: "look left".to_owned(),
+			values: vec![(YAW_PITCH, vec2(-0.5, 0.0))],
+		});
+		expressions.insert(Expression {
+			name: "look up".to_owned(),
+			values: vec![(YAW_PITCH, vec2(0.0, 0.5))],
+		});
+		expressions
+	}
+
+	#[test]
+	fn test_capture() {
+		let mut puppet = test_puppet();
+		puppet.set_param(YAW_PITCH, vec2(0.25, -0.5)).unwrap();
+		puppet.end_set_params(0.0);
+
+		let expression = Expression::capture(&puppet, "tilted", [YAW_PITCH, ParamUuid(42)]);
+		assert_eq!(expression.values, vec![(YAW_PITCH, vec2(0.25, -0.5))]);
+
+		// The physics param is moved by the simulation as well
+		let expression = Expression::capture_changed(&puppet, "tilted");
+		assert!(expression.values.contains(&(YAW_PITCH, vec2(0.25, -0.5))));
+	}
+
+	#[test]
+	fn test_vendor_data_roundtrip() {
+		let expressions = test_expressions();
+		let mut vendors = vec![VendorData {
+			name: EXPRESSIONS_VENDOR_NAME.to_owned(),
+			payload: JsonValue::Null,
+		}];
+		assert!(ExpressionSet::from_vendor_data(&vendors).is_err());
+
+		expressions.store(&mut vendors);
+		assert_eq!(vendors.len(), 1);
+		assert_eq!(ExpressionSet::from_vendor_data(&vendors).unwrap(), expressions);
+		assert_eq!(ExpressionSet::from_vendor_data(&[]).unwrap(), ExpressionSet::default());
+	}
+
+	#[test]
+	fn test_fades() {
+		let mut player = ExpressionPlayer::new();
+		player.fade_to("look left", 1.0, 1.0, Easing::Linear);
+		player.update(0.25);
+		assert_eq!(player.weight("look left"), 0.25);
+
+		player.crossfade("look up", 0.5, Easing::EaseInOut);
+		player.update(0.25);
+		assert_eq!(player.weight("look left"), 0.125);
+		assert_eq!(player.weight("look up"), 0.5);
+		player.update(1.0);
+		assert_eq!(player.weight("look left"), 0.0);
+		assert_eq!(player.weight("look up"), 1.0);
+	}
+
+	#[test]
+	fn test_layering_over_tracking() {
+		let mut puppet = test_puppet();
+		let expressions = test_expressions();
+		let mut player = ExpressionPlayer::new();
+		player.fade_to("look left", 0.5, 0.0, Easing::Linear);
+		player.fade_to("look up", 1.0, 0.0, Easing::Linear);
+
+		puppet.set_param(YAW_PITCH, vec2(0.5, 0.0)).unwrap();
+		player.apply(&expressions, &mut puppet);
+		puppet.end_set_params(0.0);
+		assert_eq!(puppet.param_value(YAW_PITCH), Some(vec2(0.25, 0.5)));
+
+		// Faded out expressions are pushed one last time, without any offset
+		player.fade_out_all(0.0, Easing::Linear);
+		puppet.set_param(YAW_PITCH, vec2(0.5, 0.0)).unwrap();
+		player.apply(&expressions, &mut puppet);
+		puppet.end_set_params(0.0);
+		assert_eq!(puppet.param_value(YAW_PITCH), Some(vec2(0.5, 0.0)));
+		assert_eq!(player.weight("look up"), 0.0);
+	}
+}
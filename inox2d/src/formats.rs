@@ -1,10 +1,84 @@
+pub mod dir;
 pub mod inp;
 mod json;
-mod payload;
+pub mod payload;
+pub mod registry;
 
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 
-pub use json::JsonError;
+pub use json::{JsonError, JsonObject};
+
+/// Bounds on what a model file may contain, to safely load untrusted files.
+///
+/// Lengths are in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+	/// Maximum length of the JSON puppet payload.
+	pub max_payload_len: usize,
+	/// Maximum nesting depth of JSON objects and lists, which also bounds the depth of the node tree.
+	pub max_json_depth: usize,
+	/// Maximum number of textures.
+	pub max_texture_count: usize,
+	/// Maximum length of a single texture.
+	pub max_texture_len: usize,
+	/// Maximum number of pixels of a single texture once decoded.
+	pub max_texture_pixels: usize,
+	/// Maximum number of vendor data entries.
+	pub max_vendor_count: usize,
+	/// Maximum length of a single vendor data name or payload.
+	pub max_vendor_len: usize,
+}
+
+impl ParseLimits {
+	/// No limits other than the 32-bit lengths of the INP format.
+	pub const UNLIMITED: Self = Self {
+		max_payload_len: usize::MAX,
+		max_json_depth: usize::MAX,
+		max_texture_count: usize::MAX,
+		max_texture_len: usize::MAX,
+		max_texture_pixels: usize::MAX,
+		max_vendor_count: usize::MAX,
+		max_vendor_len: usize::MAX,
+	};
+}
+
+impl Default for ParseLimits {
+	/// Limits well above what real models need.
+	fn default() -> Self {
+		Self {
+			max_payload_len: 256 << 20,
+			max_json_depth: 256,
+			max_texture_count: 4096,
+			max_texture_len: 512 << 20,
+			max_texture_pixels: 16384 * 16384,
+			max_vendor_count: 4096,
+			max_vendor_len: 64 << 20,
+		}
+	}
+}
+
+/// A length or count that exceeds its [`ParseLimits`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{what} of {value} exceeds the limit of {limit}")]
+pub struct LimitExceeded {
+	pub what: &'static str,
+	pub value: usize,
+	pub limit: usize,
+}
+
+/// Checks the nesting depth of a JSON text before parsing it.
+fn check_json_depth(text: &str, limits: &ParseLimits) -> Result<(), LimitExceeded> {
+	check_limit("JSON nesting depth", json::nesting_depth(text), limits.max_json_depth)?;
+	Ok(())
+}
+
+/// Checks `value` against `limit`, naming the checked quantity `what` in the error.
+pub(crate) fn check_limit(what: &'static str, value: usize, limit: usize) -> Result<usize, LimitExceeded> {
+	if value > limit {
+		return Err(LimitExceeded { what, value, limit });
+	}
+	Ok(value)
+}
 
 #[inline]
 fn read_n<R: Read, const N: usize>(data: &mut R) -> io::Result<[u8; N]> {
@@ -25,9 +99,25 @@ fn read_be_u32<R: Read>(data: &mut R) -> io::Result<u32> {
 	Ok(u32::from_be_bytes(buf))
 }
 
+/// Reads exactly `n` bytes.
+///
+/// The buffer grows with the data actually read, so a bogus length does not allocate more than the input size.
 #[inline]
 fn read_vec<R: Read>(data: &mut R, n: usize) -> io::Result<Vec<u8>> {
-	let mut buf = vec![0_u8; n];
-	data.read_exact(&mut buf)?;
+	let mut buf = Vec::new();
+	data.take(n as u64).read_to_end(&mut buf)?;
+	if buf.len() != n {
+		return Err(io::ErrorKind::UnexpectedEof.into());
+	}
 	Ok(buf)
 }
+
+#[inline]
+fn write_u8<W: Write>(data: &mut W, n: u8) -> io::Result<()> {
+	data.write_all(&n.to_ne_bytes())
+}
+
+#[inline]
+fn write_be_u32<W: Write>(data: &mut W, n: u32) -> io::Result<()> {
+	data.write_all(&n.to_be_bytes())
+}
@@ -0,0 +1,306 @@
+//! Unpacked puppet projects, laid out as:
+//!
+//! ```text
+//! puppet.json
+//! textures/0.png
+//! textures/1.tga
+//! vendor/<name>.json
+//! ```
+//!
+//! Texture files are named after their ID, and their extension gives their encoding (`png`, `tga` or `bc7`).
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::model::{Model, ModelTexture, ModelTextureFormat, VendorData};
+use crate::puppet::validation::ValidationReport;
+
+use super::json::JsonError;
+use super::payload::{
+	default_deserialize_custom, deserialize_puppet_with_diagnostics, deserialize_vendor_automations, serialize_puppet,
+	serialize_vendor_automations, InoxParseError, ParseDiagnostics,
+};
+use super::{check_json_depth, check_limit, LimitExceeded};
+
+const PUPPET_FILE: &str = "puppet.json";
+const TEXTURES_DIR: &str = "textures";
+const VENDOR_DIR: &str = "vendor";
+
+#[derive(Debug, thiserror::Error)]
+#[error("Could not parse puppet directory\n  - {0}")]
+pub enum ParseDirError {
+	#[error("unrecognized texture file {0:?}")]
+	UnknownTextureFile(PathBuf),
+	#[error("texture {0} is missing")]
+	MissingTexture(usize),
+	#[error("texture {0} is present in several formats")]
+	DuplicateTexture(usize),
+	#[error("vendor file {0:?} does not have a valid name")]
+	InvalidVendorFile(PathBuf),
+	#[error("the puppet is invalid\n{0}")]
+	Invalid(ValidationReport),
+	LimitExceeded(#[from] LimitExceeded),
+	Io(#[from] io::Error),
+	JsonParse(#[from] json::Error),
+	InoxParse(#[from] InoxParseError),
+	Json(#[from] JsonError),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Could not write puppet directory\n  - {0}")]
+pub enum WriteDirError {
+	#[error("vendor name {0:?} cannot be used as a file name")]
+	InvalidVendorName(String),
+	Io(#[from] io::Error),
+}
+
+fn format_extension(format: ModelTextureFormat) -> &'static str {
+	match format {
+		ModelTextureFormat::Png => "png",
+		ModelTextureFormat::Tga => "tga",
+		ModelTextureFormat::Bc7 => "bc7",
+	}
+}
+
+/// Get the ID and format of a texture from its file name, such as `0.png`.
+fn parse_texture_file_name(path: &Path) -> Option<(usize, ModelTextureFormat)> {
+	let id = path.file_stem()?.to_str()?.parse().ok()?;
+	let format = match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+		"png" => ModelTextureFormat::Png,
+		"tga" => ModelTextureFormat::Tga,
+		"bc7" => ModelTextureFormat::Bc7,
+		_ => return None,
+	};
+	Some((id, format))
+}
+
+fn is_hidden(path: &Path) -> bool {
+	path.file_name()
+		.and_then(|name| name.to_str())
+		.is_some_and(|name| name.starts_with('.'))
+}
+
+/// List the files of a directory in a deterministic order, skipping hidden ones.
+///
+/// A missing directory is treated as empty.
+fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+
+	let mut files = Vec::new();
+	for entry in entries {
+		let entry = entry?;
+		if entry.file_type()?.is_file() && !is_hidden(&entry.path()) {
+			files.push(entry.path());
+		}
+	}
+	files.sort();
+	Ok(files)
+}
+
+/// Parse an unpacked puppet project directory.
+///
+/// Invalid params, bindings and masks are skipped with a logged warning.
+pub fn parse_dir(dir: impl AsRef<Path>) -> Result<Model, ParseDirError> {
+	let mut diag = ParseDiagnostics::default();
+	let model = parse_dir_with_diagnostics(dir, &mut diag)?;
+	for warning in diag.warnings() {
+		tracing::warn!("Skipped invalid element: {}", warning);
+	}
+	Ok(model)
+}
+
+/// Parse an unpacked puppet project directory, handling invalid elements according to the mode of `diag`.
+pub fn parse_dir_with_diagnostics(dir: impl AsRef<Path>, diag: &mut ParseDiagnostics) -> Result<Model, ParseDirError> {
+	let dir = dir.as_ref();
+
+	let payload = fs::read_to_string(dir.join(PUPPET_FILE))?;
+	check_json_depth(&payload, diag.limits())?;
+	let payload = json::parse(&payload)?;
+	let mut puppet = deserialize_puppet_with_diagnostics(&payload, &default_deserialize_custom, diag)?;
+
+	// textures must be numbered from 0 without gaps
+	let mut texture_files = Vec::new();
+	for path in list_files(&dir.join(TEXTURES_DIR))? {
+		let (id, format) =
+			parse_texture_file_name(&path).ok_or_else(|| ParseDirError::UnknownTextureFile(path.clone()))?;
+		if id >= texture_files.len() {
+			let count = id
+				.checked_add(1)
+				.ok_or_else(|| ParseDirError::UnknownTextureFile(path.clone()))?;
+			check_limit("texture count", count, diag.limits().max_texture_count)?;
+			texture_files.resize(count, None);
+		}
+		if texture_files[id].is_some() {
+			return Err(ParseDirError::DuplicateTexture(id));
+		}
+		texture_files[id] = Some((format, path));
+	}
+
+	let mut textures = Vec::with_capacity(texture_files.len());
+	for (id, texture_file) in texture_files.into_iter().enumerate() {
+		let (format, path) = texture_file.ok_or(ParseDirError::MissingTexture(id))?;
+		let data: Arc<[u8]> = fs::read(path)?.into();
+		textures.push(ModelTexture { format, data });
+	}
+
+	let mut vendors = Vec::new();
+	for path in list_files(&dir.join(VENDOR_DIR))? {
+		if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+			continue;
+		}
+		let name = path
+			.file_stem()
+			.and_then(|name| name.to_str())
+			.ok_or_else(|| ParseDirError::InvalidVendorFile(path.clone()))?
+			.to_owned();
+
+		let payload = fs::read_to_string(&path)?;
+		check_json_depth(&payload, diag.limits())?;
+		let payload = json::parse(&payload)?;
+		vendors.push(VendorData { name, payload });
+	}
+	deserialize_vendor_automations(&mut puppet, &vendors, diag)?;
+
+	let model = Model {
+		puppet,
+		textures,
+		vendors,
+	};
+	diag.check_validity(|| model.validate())
+		.map_err(ParseDirError::Invalid)?;
+	Ok(model)
+}
+
+/// Write a model as an unpacked puppet project directory, creating it if needed.
+///
+/// Texture and vendor files already in the directory are replaced.
+/// The automations of the puppet are stored in its vendor data, see [`crate::automation`].
+/// The output can be read back with [`parse_dir`].
+pub fn write_dir(model: &Model, dir: impl AsRef<Path>) -> Result<(), WriteDirError> {
+	let dir = dir.as_ref();
+
+	let vendors = serialize_vendor_automations(&model.puppet, &model.vendors);
+	for vendor in &vendors {
+		let name = &vendor.name;
+		if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+			return Err(WriteDirError::InvalidVendorName(name.clone()));
+		}
+	}
+
+	fs::create_dir_all(dir)?;
+	let payload = json::stringify_pretty(serialize_puppet(&model.puppet), 2);
+	fs::write(dir.join(PUPPET_FILE), payload)?;
+
+	// remove stale files first, they would otherwise be picked up when parsing
+	let textures_dir = dir.join(TEXTURES_DIR);
+	for path in list_files(&textures_dir)? {
+		if parse_texture_file_name(&path).is_some() {
+			fs::remove_file(path)?;
+		}
+	}
+	let vendor_dir = dir.join(VENDOR_DIR);
+	for path in list_files(&vendor_dir)? {
+		if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+			fs::remove_file(path)?;
+		}
+	}
+
+	if !model.textures.is_empty() {
+		fs::create_dir_all(&textures_dir)?;
+	}
+	for (id, texture) in model.textures.iter().enumerate() {
+		let file_name = format!("{id}.{}", format_extension(texture.format));
+		fs::write(textures_dir.join(file_name), &texture.data)?;
+	}
+
+	if !vendors.is_empty() {
+		fs::create_dir_all(&vendor_dir)?;
+	}
+	for vendor in &vendors {
+		let payload = json::stringify_pretty(vendor.payload.clone(), 2);
+		fs::write(vendor_dir.join(format!("{}.json", vendor.name)), payload)?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+
+	fn test_model() -> Model {
+		Model {
+			puppet: test_puppet(),
+			textures: vec![
+				ModelTexture {
+					format: ModelTextureFormat::Png,
+					data: Arc::from(&b"not really a png"[..]),
+				},
+				ModelTexture {
+					format: ModelTextureFormat::Bc7,
+					data: Arc::from(&b"not really bc7"[..]),
+				},
+			],
+			vendors: vec![VendorData {
+				name: "com.inochi2d.inochi-session.bindings".to_owned(),
+				payload: json::array![{ "name": "Head", "value": 0.5 }],
+			}],
+		}
+	}
+
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("inox2d-{name}-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		dir
+	}
+
+	#[test]
+	fn test_write_parse_roundtrip() {
+		let dir = test_dir("dir-roundtrip");
+		let model = test_model();
+		write_dir(&model, &dir).unwrap();
+		assert!(dir.join("textures/1.bc7").is_file());
+
+		let parsed = parse_dir(&dir).unwrap();
+		assert_eq!(parsed.puppet.params.len(), 2);
+		assert_eq!(parsed.textures.len(), 2);
+		assert_eq!(parsed.textures[1].format, ModelTextureFormat::Bc7);
+		assert_eq!(&*parsed.textures[1].data, b"not really bc7");
+		assert_eq!(parsed.vendors.len(), 1);
+		assert_eq!(parsed.vendors[0].name, model.vendors[0].name);
+		assert_eq!(parsed.vendors[0].payload, model.vendors[0].payload);
+
+		// writing fewer textures over the same directory must not leave stale ones behind
+		let mut smaller = parsed;
+		smaller.textures.pop();
+		write_dir(&smaller, &dir).unwrap();
+		assert_eq!(parse_dir(&dir).unwrap().textures.len(), 1);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_parse_texture_gaps() {
+		let dir = test_dir("dir-gaps");
+		write_dir(&test_model(), &dir).unwrap();
+
+		fs::rename(dir.join("textures/1.bc7"), dir.join("textures/2.bc7")).unwrap();
+		assert!(matches!(parse_dir(&dir), Err(ParseDirError::MissingTexture(1))));
+
+		fs::write(dir.join("textures/2.png"), b"").unwrap();
+		assert!(matches!(parse_dir(&dir), Err(ParseDirError::DuplicateTexture(2))));
+
+		fs::remove_file(dir.join("textures/2.png")).unwrap();
+		fs::write(dir.join("textures/4294967295.png"), b"").unwrap();
+		assert!(matches!(parse_dir(&dir), Err(ParseDirError::LimitExceeded(_))));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -1,15 +1,23 @@
-use std::io::{self, Read};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 use std::sync::Arc;
 
-use image::ImageFormat;
+use crate::model::{Model, ModelTexture, ModelTextureFormat, VendorData};
+use crate::node::data::CustomNode;
+use crate::puppet::validation::ValidationReport;
+use crate::puppet::Puppet;
 
-use crate::model::{Model, ModelTexture, VendorData};
-
-use super::json::JsonError;
-use super::payload::{deserialize_puppet, InoxParseError};
-use super::{read_be_u32, read_n, read_u8, read_vec};
+use super::json::{JsonError, JsonObject};
+use super::payload::{
+	default_deserialize_custom, deserialize_puppet_with_diagnostics, deserialize_vendor_automations, serialize_puppet,
+	serialize_vendor_automations, InoxParseError, InoxParseResult, ParseDiagnostics,
+};
+use super::registry::NodeRegistry;
+use super::{
+	check_json_depth, check_limit, read_be_u32, read_n, read_u8, read_vec, write_be_u32, write_u8, LimitExceeded,
+	ParseLimits,
+};
 
 #[derive(Debug, thiserror::Error)]
 #[error("Could not parse INP file\n  - {0}")]
@@ -18,10 +26,13 @@ pub enum ParseInpError {
 	IncorrectMagic,
 	#[error("there is no texture section")]
 	NoTexSect,
-	#[error("BC7 texture encoding is not supported yet")]
-	Bc7NotSupported,
 	#[error("Invalid texture encoding: {0}")]
 	InvalidTexEncoding(u8),
+	#[error("there is no texture with ID {0}")]
+	NoTexture(usize),
+	#[error("the puppet is invalid\n{0}")]
+	Invalid(ValidationReport),
+	LimitExceeded(#[from] LimitExceeded),
 	Io(#[from] io::Error),
 	Utf8(#[from] Utf8Error),
 	FromUtf8(#[from] FromUtf8Error),
@@ -38,69 +49,557 @@ const TEX_SECT: &[u8] = b"TEX_SECT";
 const EXT_SECT: &[u8] = b"EXT_SECT";
 
 /// Parse `.inp` and `.inx` files.
-pub fn parse_inp<R: Read>(mut data: R) -> Result<Model, ParseInpError> {
-	// check magic bytes
-	let magic = read_n::<_, 8>(&mut data)?;
+///
+/// Invalid params, bindings and masks are skipped with a logged warning.
+pub fn parse_inp<R: Read>(data: R) -> Result<Model, ParseInpError> {
+	let mut diag = ParseDiagnostics::default();
+	let model = parse_inp_with_diagnostics(data, &mut diag)?;
+	log_warnings(&diag);
+	Ok(model)
+}
+
+/// Parse `.inp` and `.inx` files, handling invalid elements according to the mode of `diag`.
+pub fn parse_inp_with_diagnostics<R: Read>(data: R, diag: &mut ParseDiagnostics) -> Result<Model, ParseInpError> {
+	parse_inp_ext_with_diagnostics(data, &NodeRegistry::new(), diag)
+}
+
+/// Parse `.inp` and `.inx` files, deserializing custom node types with `registry`.
+pub fn parse_inp_ext<R: Read, T: CustomNode>(data: R, registry: &NodeRegistry<T>) -> Result<Model<T>, ParseInpError> {
+	let mut diag = ParseDiagnostics::default();
+	let model = parse_inp_ext_with_diagnostics(data, registry, &mut diag)?;
+	log_warnings(&diag);
+	Ok(model)
+}
+
+/// Combination of [`parse_inp_ext`] and [`parse_inp_with_diagnostics`].
+pub fn parse_inp_ext_with_diagnostics<R: Read, T: CustomNode>(
+	mut data: R,
+	registry: &NodeRegistry<T>,
+	diag: &mut ParseDiagnostics,
+) -> Result<Model<T>, ParseInpError> {
+	let mut puppet = read_puppet(&mut data, &|node_type, obj| registry.deserialize(node_type, obj), diag)?;
+	let limits = *diag.limits();
+
+	// retrieve textures
+	let tex_count = read_tex_sect_header(&mut data, &limits)?;
+	let mut textures = Vec::with_capacity(tex_count);
+	for _ in 0..tex_count {
+		let (format, tex_length) = read_tex_header(&mut data, &limits)?;
+		let data: Arc<[u8]> = read_vec(&mut data, tex_length)?.into();
+		textures.push(ModelTexture { format, data });
+	}
+
+	let vendors = read_vendors(&mut data, &limits)?;
+	deserialize_vendor_automations(&mut puppet, &vendors, diag)?;
+
+	let model = Model {
+		puppet,
+		textures,
+		vendors,
+	};
+	diag.check_validity(|| model.validate())
+		.map_err(ParseInpError::Invalid)?;
+	Ok(model)
+}
+
+/// Check magic bytes and parse the json payload into a puppet.
+fn read_puppet<R: Read, T: CustomNode>(
+	data: &mut R,
+	deserialize_node_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
+	diag: &mut ParseDiagnostics,
+) -> Result<Puppet<T>, ParseInpError> {
+	let magic = read_n::<_, 8>(data)?;
 	if magic != MAGIC {
 		return Err(ParseInpError::IncorrectMagic);
 	}
 
-	// parse json payload into puppet
-	let length = read_be_u32(&mut data)? as usize;
-	let payload = read_vec(&mut data, length)?;
+	let length = check_limit(
+		"payload length",
+		read_be_u32(data)? as usize,
+		diag.limits().max_payload_len,
+	)?;
+	let payload = read_vec(data, length)?;
 	let payload = std::str::from_utf8(&payload)?;
+	check_json_depth(payload, diag.limits())?;
 	let payload = json::parse(payload)?;
-	let puppet = deserialize_puppet(&payload)?;
+	Ok(deserialize_puppet_with_diagnostics(
+		&payload,
+		deserialize_node_custom,
+		diag,
+	)?)
+}
 
-	// check texture section header
-	let tex_sect = read_n::<_, 8>(&mut data).map_err(|_| ParseInpError::NoTexSect)?;
+fn log_warnings(diag: &ParseDiagnostics) {
+	for warning in diag.warnings() {
+		tracing::warn!("Skipped invalid element: {}", warning);
+	}
+}
+
+/// Check the texture section header and return the number of textures.
+fn read_tex_sect_header<R: Read>(data: &mut R, limits: &ParseLimits) -> Result<usize, ParseInpError> {
+	let tex_sect = read_n::<_, 8>(data).map_err(|_| ParseInpError::NoTexSect)?;
 	if tex_sect != TEX_SECT {
 		return Err(ParseInpError::NoTexSect);
 	}
 
-	// retrieve textures
-	let tex_count = read_be_u32(&mut data)? as usize;
-	let mut textures = Vec::with_capacity(tex_count);
-	for _ in 0..tex_count {
-		let tex_length = read_be_u32(&mut data)? as usize;
-		let tex_encoding = read_u8(&mut data)?;
-
-		let format = match tex_encoding {
-			0 => ImageFormat::Png, // PNG
-			1 => ImageFormat::Tga, // TGA
-			2 => return Err(ParseInpError::Bc7NotSupported),
-			n => return Err(ParseInpError::InvalidTexEncoding(n)),
-		};
+	Ok(check_limit(
+		"texture count",
+		read_be_u32(data)? as usize,
+		limits.max_texture_count,
+	)?)
+}
 
-		let data: Arc<[u8]> = read_vec(&mut data, tex_length)?.into();
-		textures.push(ModelTexture { format, data });
-	}
+/// Read the format and length of the texture blob that follows.
+fn read_tex_header<R: Read>(data: &mut R, limits: &ParseLimits) -> Result<(ModelTextureFormat, usize), ParseInpError> {
+	let tex_length = check_limit("texture length", read_be_u32(data)? as usize, limits.max_texture_len)?;
+	let tex_encoding = read_u8(data)?;
+
+	let format = match tex_encoding {
+		0 => ModelTextureFormat::Png,
+		1 => ModelTextureFormat::Tga,
+		2 => ModelTextureFormat::Bc7,
+		n => return Err(ParseInpError::InvalidTexEncoding(n)),
+	};
 
-	// read extended section header if present
-	let vendors = match read_n::<_, 8>(&mut data) {
+	Ok((format, tex_length))
+}
+
+/// Read the extended section if present.
+fn read_vendors<R: Read>(data: &mut R, limits: &ParseLimits) -> Result<Vec<VendorData>, ParseInpError> {
+	match read_n::<_, 8>(data) {
 		Ok(ext_sect) if ext_sect == EXT_SECT => {
-			let ext_count = read_be_u32(&mut data)? as usize;
+			let ext_count = check_limit(
+				"vendor data count",
+				read_be_u32(data)? as usize,
+				limits.max_vendor_count,
+			)?;
 			let mut vendors = Vec::with_capacity(ext_count);
 			for _ in 0..ext_count {
-				let length = read_be_u32(&mut data)? as usize;
-				let name = read_vec(&mut data, length)?;
+				let length = check_limit("vendor name length", read_be_u32(data)? as usize, limits.max_vendor_len)?;
+				let name = read_vec(data, length)?;
 				let name = String::from_utf8(name)?;
 
-				let length = read_be_u32(&mut data)? as usize;
-				let payload = read_vec(&mut data, length)?;
+				let length = check_limit(
+					"vendor payload length",
+					read_be_u32(data)? as usize,
+					limits.max_vendor_len,
+				)?;
+				let payload = read_vec(data, length)?;
 				let payload = std::str::from_utf8(&payload)?;
+				check_json_depth(payload, limits)?;
 				let payload = json::parse(payload)?;
 
 				vendors.push(VendorData { name, payload });
 			}
-			vendors
+			Ok(vendors)
 		}
-		_ => Vec::new(),
-	};
+		_ => Ok(Vec::new()),
+	}
+}
 
-	Ok(Model {
-		puppet,
-		textures,
-		vendors,
-	})
+/// Location of a texture inside an INP file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InpTextureEntry {
+	pub format: ModelTextureFormat,
+	/// Offset of the texture data from the start of the file.
+	pub offset: u64,
+	pub length: usize,
+}
+
+/// INP file whose textures are only read when asked for.
+///
+/// Opening it parses the puppet and the vendor data, but only indexes where each texture lives.
+/// Useful to browse many models without decoding all of their textures.
+#[derive(Debug)]
+pub struct LazyInp<R> {
+	data: R,
+	puppet: Puppet,
+	textures: Vec<InpTextureEntry>,
+	vendors: Vec<VendorData>,
+}
+
+impl<R: Read + Seek> LazyInp<R> {
+	/// Parse the puppet of an `.inp` or `.inx` file and index its textures.
+	///
+	/// Invalid params, bindings and masks are skipped with a logged warning.
+	pub fn open(data: R) -> Result<Self, ParseInpError> {
+		let mut diag = ParseDiagnostics::default();
+		let lazy = Self::open_with_diagnostics(data, &mut diag)?;
+		log_warnings(&diag);
+		Ok(lazy)
+	}
+
+	/// Like [`LazyInp::open`], handling invalid elements according to the mode of `diag`.
+	pub fn open_with_diagnostics(mut data: R, diag: &mut ParseDiagnostics) -> Result<Self, ParseInpError> {
+		let mut puppet = read_puppet(&mut data, &default_deserialize_custom, diag)?;
+		let limits = *diag.limits();
+
+		let tex_count = read_tex_sect_header(&mut data, &limits)?;
+
+		// seeking past the end succeeds, so check texture bounds against the stream length
+		let sect_start = data.stream_position()?;
+		let stream_len = data.seek(SeekFrom::End(0))?;
+		data.seek(SeekFrom::Start(sect_start))?;
+
+		let mut textures = Vec::with_capacity(tex_count);
+		for _ in 0..tex_count {
+			let (format, length) = read_tex_header(&mut data, &limits)?;
+			let offset = data.stream_position()?;
+			let end = offset + length as u64;
+			if end > stream_len {
+				return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+			}
+			data.seek(SeekFrom::Start(end))?;
+			textures.push(InpTextureEntry { format, offset, length });
+		}
+
+		let vendors = read_vendors(&mut data, &limits)?;
+		deserialize_vendor_automations(&mut puppet, &vendors, diag)?;
+
+		diag.check_validity(|| {
+			let mut report = puppet.validate();
+			report.extend(puppet.validate_textures(textures.len()));
+			report
+		})
+		.map_err(ParseInpError::Invalid)?;
+
+		Ok(Self {
+			data,
+			puppet,
+			textures,
+			vendors,
+		})
+	}
+
+	/// Read the texture with the given ID.
+	pub fn read_texture(&mut self, id: usize) -> Result<ModelTexture, ParseInpError> {
+		let entry = *self.textures.get(id).ok_or(ParseInpError::NoTexture(id))?;
+
+		self.data.seek(SeekFrom::Start(entry.offset))?;
+		let data: Arc<[u8]> = read_vec(&mut self.data, entry.length)?.into();
+		Ok(ModelTexture {
+			format: entry.format,
+			data,
+		})
+	}
+
+	/// Read the thumbnail referenced by the puppet's metadata, if it has one.
+	pub fn read_thumbnail(&mut self) -> Result<Option<ModelTexture>, ParseInpError> {
+		match self.puppet.meta.thumbnail_id {
+			Some(id) if id != u32::MAX => self.read_texture(id as usize).map(Some),
+			_ => Ok(None),
+		}
+	}
+
+	/// Read all textures, giving the same model as [`parse_inp`] would.
+	pub fn into_model(mut self) -> Result<Model, ParseInpError> {
+		let textures = (0..self.textures.len())
+			.map(|id| self.read_texture(id))
+			.collect::<Result<_, _>>()?;
+
+		Ok(Model {
+			puppet: self.puppet,
+			textures,
+			vendors: self.vendors,
+		})
+	}
+}
+
+impl<R> LazyInp<R> {
+	pub fn puppet(&self) -> &Puppet {
+		&self.puppet
+	}
+
+	pub fn vendors(&self) -> &[VendorData] {
+		&self.vendors
+	}
+
+	pub fn textures(&self) -> &[InpTextureEntry] {
+		&self.textures
+	}
+
+	pub fn into_inner(self) -> R {
+		self.data
+	}
+}
+
+impl<B: AsRef<[u8]>> LazyInp<io::Cursor<B>> {
+	/// Borrow the data of the texture with the given ID without copying it.
+	///
+	/// Returns `None` if there is no such texture or if it goes past the end of the buffer.
+	pub fn texture_bytes(&self, id: usize) -> Option<&[u8]> {
+		let entry = self.textures.get(id)?;
+		let start = usize::try_from(entry.offset).ok()?;
+		self.data
+			.get_ref()
+			.as_ref()
+			.get(start..start.checked_add(entry.length)?)
+	}
+}
+
+/// Memory-map an `.inp` or `.inx` file and index it.
+///
+/// Textures can then be borrowed straight from the mapping with [`LazyInp::texture_bytes`].
+///
+/// # Safety
+///
+/// The file must not be modified or truncated while it is mapped, see [`memmap2::Mmap::map`].
+#[cfg(feature = "mmap")]
+pub unsafe fn open_inp_mmap(file: &std::fs::File) -> Result<LazyInp<io::Cursor<memmap2::Mmap>>, ParseInpError> {
+	let mmap = memmap2::Mmap::map(file)?;
+	LazyInp::open(io::Cursor::new(mmap))
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Could not write INP file\n  - {0}")]
+pub enum WriteInpError {
+	#[error("Length {0} does not fit in 32 bits")]
+	LengthTooLarge(usize),
+	Io(#[from] io::Error),
+}
+
+fn write_length<W: Write>(data: &mut W, length: usize) -> Result<(), WriteInpError> {
+	let length = u32::try_from(length).map_err(|_| WriteInpError::LengthTooLarge(length))?;
+	Ok(write_be_u32(data, length)?)
+}
+
+/// Write a model in the `.inp` format.
+///
+/// The automations of the puppet are stored in its vendor data, see [`crate::automation`].
+/// The output can be read back with [`parse_inp`].
+pub fn write_inp<W: Write>(model: &Model, data: W) -> Result<(), WriteInpError> {
+	let vendors = serialize_vendor_automations(&model.puppet, &model.vendors);
+	write_inp_payload(&serialize_puppet(&model.puppet), &model.textures, &vendors, data)
+}
+
+/// Write a puppet payload and its textures and vendor data in the `.inp` format.
+///
+/// The output can be read back with [`parse_inp`].
+pub fn write_inp_payload<W: Write>(
+	payload: &json::JsonValue,
+	textures: &[ModelTexture],
+	vendors: &[VendorData],
+	mut data: W,
+) -> Result<(), WriteInpError> {
+	data.write_all(MAGIC)?;
+
+	// write puppet as json payload
+	let payload = json::stringify(payload.clone());
+	write_length(&mut data, payload.len())?;
+	data.write_all(payload.as_bytes())?;
+
+	// write texture section
+	data.write_all(TEX_SECT)?;
+	write_length(&mut data, textures.len())?;
+	for texture in textures {
+		let tex_encoding = match texture.format {
+			ModelTextureFormat::Png => 0,
+			ModelTextureFormat::Tga => 1,
+			ModelTextureFormat::Bc7 => 2,
+		};
+
+		write_length(&mut data, texture.data.len())?;
+		write_u8(&mut data, tex_encoding)?;
+		data.write_all(&texture.data)?;
+	}
+
+	// write extended section only if there is vendor data to store
+	if !vendors.is_empty() {
+		data.write_all(EXT_SECT)?;
+		write_length(&mut data, vendors.len())?;
+		for vendor in vendors {
+			write_length(&mut data, vendor.name.len())?;
+			data.write_all(vendor.name.as_bytes())?;
+
+			let payload = json::stringify(vendor.payload.clone());
+			write_length(&mut data, payload.len())?;
+			data.write_all(payload.as_bytes())?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::automation::{Automation, Generator, AUTOMATIONS_VENDOR_NAME};
+	use crate::formats::payload::tests::{test_payload, test_puppet};
+	use crate::formats::payload::ParseMode;
+	use crate::node::data::InoxData;
+	use crate::node::InoxNodeUuid;
+
+	fn test_model() -> Model {
+		Model {
+			puppet: test_puppet(),
+			textures: vec![
+				ModelTexture {
+					format: ModelTextureFormat::Png,
+					data: Arc::from(&b"not really a png"[..]),
+				},
+				ModelTexture {
+					format: ModelTextureFormat::Tga,
+					data: Arc::from(&b"not really a tga"[..]),
+				},
+			],
+			vendors: vec![VendorData {
+				name: "com.inochi2d.inochi-session.bindings".to_owned(),
+				payload: json::array![{ "name": "Head", "value": 0.5 }],
+			}],
+		}
+	}
+
+	#[test]
+	fn test_write_parse_roundtrip() {
+		let mut written = Vec::new();
+		write_inp(&test_model(), &mut written).unwrap();
+
+		let model = parse_inp(written.as_slice()).unwrap();
+		assert_eq!(model.puppet.params.len(), 2);
+		assert_eq!(model.textures.len(), 2);
+		assert_eq!(model.textures[1].format, ModelTextureFormat::Tga);
+		assert_eq!(&*model.textures[1].data, b"not really a tga");
+		assert_eq!(model.vendors.len(), 1);
+		assert_eq!(model.vendors[0].payload[0]["value"], 0.5);
+
+		let mut rewritten = Vec::new();
+		write_inp(&model, &mut rewritten).unwrap();
+		assert_eq!(written, rewritten);
+	}
+
+	#[test]
+	fn test_automations_roundtrip() {
+		let mut model = test_model();
+		let automation = Automation::new("blink", Generator::blink(), Vec::new());
+		model.puppet.data_mut().automations.push(automation.clone());
+
+		let mut written = Vec::new();
+		write_inp(&model, &mut written).unwrap();
+
+		let parsed = parse_inp(written.as_slice()).unwrap();
+		assert_eq!(parsed.puppet.automations, vec![automation]);
+		assert_eq!(parsed.vendors.len(), 2);
+		assert_eq!(parsed.vendors[1].name, AUTOMATIONS_VENDOR_NAME);
+		assert_eq!(
+			LazyInp::open(io::Cursor::new(written))
+				.unwrap()
+				.puppet()
+				.automations
+				.len(),
+			1
+		);
+	}
+
+	#[test]
+	fn test_write_without_vendor_data() {
+		let mut model = test_model();
+		model.vendors.clear();
+
+		let mut written = Vec::new();
+		write_inp(&model, &mut written).unwrap();
+		assert!(!written.windows(EXT_SECT.len()).any(|window| window == EXT_SECT));
+		assert!(parse_inp(written.as_slice()).unwrap().vendors.is_empty());
+	}
+
+	#[test]
+	fn test_lazy_open() {
+		let mut model = test_model();
+		model.puppet.data_mut().meta.thumbnail_id = Some(1);
+
+		let mut written = Vec::new();
+		write_inp(&model, &mut written).unwrap();
+
+		let mut lazy = LazyInp::open(io::Cursor::new(written.as_slice())).unwrap();
+		assert_eq!(lazy.puppet().params.len(), 2);
+		assert_eq!(lazy.vendors().len(), 1);
+		assert_eq!(lazy.textures().len(), 2);
+		assert_eq!(lazy.textures()[0].format, ModelTextureFormat::Png);
+		assert_eq!(lazy.texture_bytes(0), Some(&b"not really a png"[..]));
+		assert!(lazy.texture_bytes(2).is_none());
+
+		let thumbnail = lazy.read_thumbnail().unwrap().unwrap();
+		assert_eq!(thumbnail.format, ModelTextureFormat::Tga);
+		assert_eq!(&*thumbnail.data, b"not really a tga");
+		assert!(matches!(lazy.read_texture(2), Err(ParseInpError::NoTexture(2))));
+
+		let lazy_model = lazy.into_model().unwrap();
+		let mut rewritten = Vec::new();
+		write_inp(&lazy_model, &mut rewritten).unwrap();
+		assert_eq!(written, rewritten);
+	}
+
+	#[test]
+	fn test_lazy_open_truncated() {
+		let mut written = Vec::new();
+		write_inp(&test_model(), &mut written).unwrap();
+
+		// cut the file in the middle of the last texture
+		let tga_start = written
+			.windows(16)
+			.position(|window| window == b"not really a tga")
+			.unwrap();
+		written.truncate(tga_start + 4);
+
+		assert!(parse_inp(written.as_slice()).is_err());
+		assert!(matches!(
+			LazyInp::open(io::Cursor::new(written.as_slice())),
+			Err(ParseInpError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+		));
+	}
+
+	#[test]
+	fn test_validation_at_load() {
+		let mut written = Vec::new();
+		write_inp(&test_model(), &mut written).unwrap();
+
+		// the fixture uses more textures than the test model has
+		let mut diag = ParseDiagnostics::new(ParseMode::Lenient).with_validation();
+		parse_inp_with_diagnostics(written.as_slice(), &mut diag).unwrap();
+		assert!(!diag.validation().unwrap().is_valid());
+
+		let mut diag = ParseDiagnostics::new(ParseMode::Strict).with_validation();
+		let result = parse_inp_with_diagnostics(written.as_slice(), &mut diag);
+		assert!(matches!(result, Err(ParseInpError::Invalid(_))));
+	}
+
+	#[derive(Debug, PartialEq)]
+	struct Camera {
+		fov: f32,
+	}
+
+	impl CustomNode for Camera {}
+
+	#[test]
+	fn test_parse_custom_nodes() {
+		let mut payload = test_payload();
+		let mut camera = payload["nodes"]["children"][1].clone();
+		camera["uuid"] = 7.into();
+		camera["type"] = "Camera".into();
+		camera["fov"] = 90.into();
+		camera.remove("children");
+		payload["nodes"]["children"].push(camera).unwrap();
+
+		let payload = json::stringify(payload);
+		let mut data = MAGIC.to_vec();
+		write_be_u32(&mut data, payload.len() as u32).unwrap();
+		data.extend_from_slice(payload.as_bytes());
+		data.extend_from_slice(TEX_SECT);
+		write_be_u32(&mut data, 0).unwrap();
+
+		assert!(matches!(
+			parse_inp(data.as_slice()),
+			Err(ParseInpError::InoxParse(InoxParseError::UnknownNodeType(_)))
+		));
+
+		let mut registry = NodeRegistry::new();
+		registry.register("Camera", |obj| {
+			Ok(Camera {
+				fov: obj.get_f32("fov")?,
+			})
+		});
+		let model = parse_inp_ext(data.as_slice(), &registry).unwrap();
+
+		let camera = model.puppet.nodes.get_node(InoxNodeUuid(7)).unwrap();
+		assert!(matches!(camera.data, InoxData::Custom(Camera { fov }) if fov == 90.0));
+	}
 }
@@ -27,6 +27,14 @@ pub enum JsonError {
 	KeyDoesNotExist(String),
 	#[error("Value at {0:?} is not an object")]
 	ValueIsNotObject(String),
+	#[error("List element is not an object")]
+	ElementIsNotObject,
+	#[error("List element is not a number")]
+	ElementIsNotNumber,
+	#[error("List element is not a bool")]
+	ElementIsNotBool,
+	#[error("List element is out of range")]
+	ElementOutOfRange,
 	#[error("Value at {0:?} is not a list")]
 	ValueIsNotList(String),
 	#[error("Value at {0:?} is not a string")]
@@ -54,6 +62,93 @@ impl JsonError {
 			source: Box::new(self),
 		}
 	}
+
+	pub fn in_list(self, index: usize) -> Self {
+		Self::ErrorInList {
+			index,
+			source: Box::new(self),
+		}
+	}
+
+	/// Path to the value that caused the error, such as `bindings[1].node`.
+	pub fn path(&self) -> String {
+		let mut path = String::new();
+		let mut error = self;
+		loop {
+			match error {
+				Self::ErrorInList { index, source } => {
+					path.push_str(&format!("[{index}]"));
+					error = source;
+				}
+				Self::ErrorInObject { key, source } => {
+					if !path.is_empty() {
+						path.push('.');
+					}
+					path.push_str(key);
+					error = source;
+				}
+				Self::ElementIsNotObject
+				| Self::ElementIsNotNumber
+				| Self::ElementIsNotBool
+				| Self::ElementOutOfRange => return path,
+				Self::KeyDoesNotExist(key)
+				| Self::ValueIsNotObject(key)
+				| Self::ValueIsNotList(key)
+				| Self::ValueIsNotString(key)
+				| Self::ValueIsNotNumber(key)
+				| Self::ValueIsNotBool(key)
+				| Self::ParseIntError(key)
+				| Self::ParseVec2Error { key, .. }
+				| Self::ParseVec3Error { key, .. } => {
+					if !path.is_empty() {
+						path.push('.');
+					}
+					path.push_str(key);
+					return path;
+				}
+			}
+		}
+	}
+}
+
+/// Converts a JSON number to `f32` by going through `f64`.
+///
+/// The direct `f32` conversion of the `json` crate accumulates rounding errors,
+/// which would prevent floats from surviving a serialization round-trip.
+pub(super) fn number_to_f32(number: json::number::Number) -> f32 {
+	f64::from(number) as f32
+}
+
+/// Returns the maximum nesting depth of objects and lists in a JSON text, without parsing it.
+pub(super) fn nesting_depth(text: &str) -> usize {
+	let mut depth = 0_usize;
+	let mut max_depth = 0;
+	let mut in_string = false;
+	let mut escaped = false;
+
+	for byte in text.bytes() {
+		if in_string {
+			match byte {
+				_ if escaped => escaped = false,
+				b'\\' => escaped = true,
+				b'"' => in_string = false,
+				_ => (),
+			}
+			continue;
+		}
+
+		match byte {
+			b'"' => in_string = true,
+			b'[' | b'{' => {
+				depth += 1;
+				max_depth = max_depth.max(depth);
+			}
+			b']' | b'}' => depth = depth.saturating_sub(1),
+			_ => (),
+		}
+	}
+
+	max_depth
 }
 
 pub struct JsonObject<'a>(pub &'a json::object::Object);
@@ -67,7 +162,7 @@ impl<'a> JsonObject<'a> {
 		}
 	}
 
-	pub fn get_object(&self, key: &str) -> JsonResult<JsonObject> {
+	pub fn get_object(&self, key: &str) -> JsonResult<JsonObject<'_>> {
 		match self.get(key)?.as_object() {
 			Some(obj) => Ok(JsonObject(obj)),
 			None => Err(JsonError::ValueIsNotObject(key.to_owned())),
@@ -111,7 +206,7 @@ impl<'a> JsonObject<'a> {
 	}
 
 	pub fn get_f32(&self, key: &str) -> JsonResult<f32> {
-		Ok(self.get_number(key)?.into())
+		Ok(number_to_f32(self.get_number(key)?))
 	}
 
 	pub fn get_u64(&self, key: &str) -> JsonResult<u64> {
@@ -191,7 +286,7 @@ impl<'a> JsonObject<'a> {
 		}
 
 		let x = match list[0].as_number() {
-			Some(val) => val.into(),
+			Some(val) => number_to_f32(val),
 			None => {
 				return Err(JsonError::ParseVec2Error {
 					key: key.to_owned(),
@@ -201,7 +296,7 @@ impl<'a> JsonObject<'a> {
 		};
 
 		let y = match list[1].as_number() {
-			Some(val) => val.into(),
+			Some(val) => number_to_f32(val),
 			None => {
 				return Err(JsonError::ParseVec2Error {
 					key: key.to_owned(),
@@ -222,7 +317,7 @@ impl<'a> JsonObject<'a> {
 		}
 
 		let x = match list[0].as_number() {
-			Some(val) => val.into(),
+			Some(val) => number_to_f32(val),
 			None => {
 				return Err(JsonError::ParseVec3Error {
 					key: key.to_owned(),
@@ -232,7 +327,7 @@ impl<'a> JsonObject<'a> {
 		};
 
 		let y = match list[1].as_number() {
-			Some(val) => val.into(),
+			Some(val) => number_to_f32(val),
 			None => {
 				return Err(JsonError::ParseVec3Error {
 					key: key.to_owned(),
@@ -242,7 +337,7 @@ impl<'a> JsonObject<'a> {
 		};
 
 		let z = match list[2].as_number() {
-			Some(val) => val.into(),
+			Some(val) => number_to_f32(val),
 			None => {
 				return Err(JsonError::ParseVec3Error {
 					key: key.to_owned(),
@@ -1,28 +1,34 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use glam::{vec2, vec3, Vec2};
+use glam::{vec2, vec3, Vec2, Vec3};
 use indextree::Arena;
 use json::JsonValue;
 
+use crate::animation::{Animation, AnimationLane, Keyframe};
+use crate::automation::{Automation, AutomationBinding, Generator, WaveShape, AUTOMATIONS_VENDOR_NAME};
 use crate::math::interp::InterpolateMode;
 use crate::math::matrix::{Matrix2d, Matrix2dFromSliceVecsError};
 use crate::math::transform::TransformOffset;
 use crate::mesh::{f32s_as_vec2s, Mesh};
+use crate::model::VendorData;
 use crate::node::data::{
-	BlendMode, Composite, Drawable, InoxData, Mask, MaskMode, ParamMapMode, Part, PhysicsModel, PhysicsProps,
-	SimplePhysics,
+	BlendMode, Composite, CustomNode, Drawable, InoxData, Mask, MaskMode, ParamMapMode, Part, PhysicsModel,
+	PhysicsProps, SimplePhysics,
 };
 use crate::node::tree::InoxNodeTree;
 use crate::node::{InoxNode, InoxNodeUuid};
-use crate::params::{AxisPoints, Binding, BindingValues, Param, ParamUuid};
-use crate::physics::runge_kutta::PhysicsState;
+use crate::params::{AxisPoints, Binding, BindingValues, Param, ParamGroup, ParamMergeMode, ParamUuid};
+use crate::puppet::validation::ValidationReport;
 use crate::puppet::{
-	Puppet, PuppetAllowedModification, PuppetAllowedRedistribution, PuppetAllowedUsers, PuppetMeta, PuppetPhysics,
-	PuppetUsageRights,
+	Puppet, PuppetAllowedModification, PuppetAllowedRedistribution, PuppetAllowedUsers, PuppetData, PuppetMeta,
+	PuppetPhysics, PuppetUsageRights,
 };
+use crate::render::VertexBuffers;
 use crate::texture::TextureId;
 
-use super::json::{JsonError, JsonObject, SerialExtend};
+use super::json::{number_to_f32, JsonError, JsonObject, SerialExtend};
+use super::{LimitExceeded, ParseLimits};
 
 pub type InoxParseResult<T> = Result<T, InoxParseError>;
 
@@ -40,10 +46,24 @@ pub enum InoxParseError {
 	InvalidMatrix2dData(#[from] Matrix2dFromSliceVecsError),
 	#[error("Unknown param map mode {0:?}")]
 	UnknownParamMapMode(String),
+	#[error("Unknown physics model {0:?}")]
+	UnknownPhysicsModel(String),
 	#[error("Unknown mask mode {0:?}")]
 	UnknownMaskMode(String),
 	#[error("Unknown interpolate mode {0:?}")]
 	UnknownInterpolateMode(String),
+	#[error("Unknown merge mode {0:?}")]
+	UnknownMergeMode(String),
+	#[error("Unknown automation type {0:?}")]
+	UnknownAutomationType(String),
+	#[error("Unknown wave shape {0:?}")]
+	UnknownWaveShape(String),
+	#[error("Blink needs a positive interval and duration and a variance from 0 up to 1, excluding 1, got interval {interval}, variance {variance} and duration {duration}")]
+	InvalidBlink {
+		interval: f32,
+		variance: f32,
+		duration: f32,
+	},
 	#[error("Unknown allowed users {0:?}")]
 	UnknownPuppetAllowedUsers(String),
 	#[error("Unknown allowed redistribution {0:?}")]
@@ -54,6 +74,12 @@ pub enum InoxParseError {
 	OddNumberOfFloatsInList(usize),
 	#[error("Expected 2 floats in list, got {0}")]
 	Not2FloatsInList(usize),
+	#[error("Expected 2 lists of axis points, got {0}")]
+	Not2AxisPointLists(usize),
+	#[error(transparent)]
+	LimitExceeded(#[from] LimitExceeded),
+	#[error("Invalid element at {}\n  - {}", .0.path, .0.error)]
+	InvalidElement(Box<ParseWarning>),
 }
 
 impl InoxParseError {
@@ -65,6 +91,132 @@ impl InoxParseError {
 	}
 }
 
+/// How to deal with list elements (params, bindings, masks) that fail to parse.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+	/// Fail on the first invalid element.
+	Strict,
+	/// Skip invalid elements and record a warning for each of them.
+	#[default]
+	Lenient,
+}
+
+/// An element that was skipped while parsing a puppet.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{path}: {error}")]
+pub struct ParseWarning {
+	/// Full JSON path to the invalid value, such as `param[0].bindings[1].node`.
+	pub path: String,
+	pub error: InoxParseError,
+}
+
+/// Collects the elements skipped while parsing a puppet.
+///
+/// Model loaders can also validate the puppet they load, see [`ParseDiagnostics::with_validation`].
+#[derive(Clone, Debug, Default)]
+pub struct ParseDiagnostics {
+	mode: ParseMode,
+	warnings: Vec<ParseWarning>,
+	validate: bool,
+	validation: Option<ValidationReport>,
+	limits: ParseLimits,
+}
+
+impl ParseDiagnostics {
+	pub fn new(mode: ParseMode) -> Self {
+		Self {
+			mode,
+			warnings: Vec::new(),
+			validate: false,
+			validation: None,
+			limits: ParseLimits::default(),
+		}
+	}
+
+	/// Set the limits model loaders enforce, [`ParseLimits::default`] otherwise.
+	pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+		self.limits = limits;
+		self
+	}
+
+	pub fn limits(&self) -> &ParseLimits {
+		&self.limits
+	}
+
+	/// Have model loaders run [`Puppet::validate`] once the model is loaded.
+	///
+	/// In strict mode, an invalid model fails to load.
+	pub fn with_validation(mut self) -> Self {
+		self.validate = true;
+		self
+	}
+
+	/// Report of the validation done at load, if it was enabled.
+	pub fn validation(&self) -> Option<&ValidationReport> {
+		self.validation.as_ref()
+	}
+
+	/// Validates a loaded model if enabled, returning the report as an error if it is invalid in strict mode.
+	pub(crate) fn check_validity(
+		&mut self,
+		validate: impl FnOnce() -> ValidationReport,
+	) -> Result<(), ValidationReport> {
+		if !self.validate {
+			return Ok(());
+		}
+
+		let report = validate();
+		if self.mode == ParseMode::Strict && !report.is_valid() {
+			return Err(report);
+		}
+		self.validation = Some(report);
+		Ok(())
+	}
+
+	pub fn mode(&self) -> ParseMode {
+		self.mode
+	}
+
+	pub fn warnings(&self) -> &[ParseWarning] {
+		&self.warnings
+	}
+
+	pub fn into_warnings(self) -> Vec<ParseWarning> {
+		self.warnings
+	}
+
+	/// Records that the element at `path` was skipped because of `error`.
+	///
+	/// In strict mode, the error is returned instead.
+	fn skip(&mut self, path: String, error: InoxParseError) -> InoxParseResult<()> {
+		// A nested element already failed in strict mode
+		if let InoxParseError::InvalidElement(_) = error {
+			return Err(error);
+		}
+
+		let path = match &error {
+			InoxParseError::JsonError(json_error) => {
+				let inner = json_error.path();
+				if inner.is_empty() || inner.starts_with('[') {
+					path + &inner
+				} else {
+					format!("{path}.{inner}")
+				}
+			}
+			_ => path,
+		};
+		let warning = ParseWarning { path, error };
+
+		match self.mode {
+			ParseMode::Strict => Err(InoxParseError::InvalidElement(Box::new(warning))),
+			ParseMode::Lenient => {
+				self.warnings.push(warning);
+				Ok(())
+			}
+		}
+	}
+}
+
 fn vals<T>(key: &str, res: InoxParseResult<T>) -> InoxParseResult<T> {
 	res.map_err(|e| e.nested(key))
 }
@@ -76,13 +228,22 @@ fn as_nested_list(index: usize, val: &json::JsonValue) -> InoxParseResult<&[json
 	}
 }
 
-fn default_deserialize_custom<T>(node_type: &str, _obj: &JsonObject) -> InoxParseResult<T> {
+fn as_object(val: &json::JsonValue) -> InoxParseResult<JsonObject<'_>> {
+	match val.as_object() {
+		Some(obj) => Ok(JsonObject(obj)),
+		None => Err(InoxParseError::JsonError(JsonError::ElementIsNotObject)),
+	}
+}
+
+pub(super) fn default_deserialize_custom<T>(node_type: &str, _obj: &JsonObject) -> InoxParseResult<T> {
 	Err(InoxParseError::UnknownNodeType(node_type.to_owned()))
 }
 
 fn deserialize_node<T>(
 	obj: &JsonObject,
 	deserialize_node_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
+	diag: &mut ParseDiagnostics,
+	path: &str,
 ) -> InoxParseResult<InoxNode<T>> {
 	let node_type = obj.get_str("type")?;
 	Ok(InoxNode {
@@ -92,7 +253,10 @@ fn deserialize_node<T>(
 		zsort: obj.get_f32("zsort")?,
 		trans_offset: vals("transform", deserialize_transform(&obj.get_object("transform")?))?,
 		lock_to_root: obj.get_bool("lockToRoot")?,
-		data: vals("data", deserialize_node_data(node_type, obj, deserialize_node_custom))?,
+		data: vals(
+			"data",
+			deserialize_node_data(node_type, obj, deserialize_node_custom, diag, path),
+		)?,
 	})
 }
 
@@ -100,17 +264,19 @@ fn deserialize_node_data<T>(
 	node_type: &str,
 	obj: &JsonObject,
 	deserialize_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
+	diag: &mut ParseDiagnostics,
+	path: &str,
 ) -> InoxParseResult<InoxData<T>> {
 	Ok(match node_type {
 		"Node" => InoxData::Node,
-		"Part" => InoxData::Part(deserialize_part(obj)?),
-		"Composite" => InoxData::Composite(deserialize_composite(obj)?),
+		"Part" => InoxData::Part(deserialize_part(obj, diag, path)?),
+		"Composite" => InoxData::Composite(deserialize_composite(obj, diag, path)?),
 		"SimplePhysics" => InoxData::SimplePhysics(deserialize_simple_physics(obj)?),
 		node_type => InoxData::Custom((deserialize_custom)(node_type, obj)?),
 	})
 }
 
-fn deserialize_part(obj: &JsonObject) -> InoxParseResult<Part> {
+fn deserialize_part(obj: &JsonObject, diag: &mut ParseDiagnostics, path: &str) -> InoxParseResult<Part> {
 	let (tex_albedo, tex_emissive, tex_bumpmap) = {
 		let textures = obj.get_list("textures")?;
 
@@ -144,7 +310,7 @@ fn deserialize_part(obj: &JsonObject) -> InoxParseResult<Part> {
 	};
 
 	Ok(Part {
-		draw_state: deserialize_drawable(obj)?,
+		draw_state: deserialize_drawable(obj, diag, path)?,
 		mesh: vals("mesh", deserialize_mesh(&obj.get_object("mesh")?))?,
 		tex_albedo,
 		tex_emissive,
@@ -152,8 +318,8 @@ fn deserialize_part(obj: &JsonObject) -> InoxParseResult<Part> {
 	})
 }
 
-fn deserialize_composite(obj: &JsonObject) -> InoxParseResult<Composite> {
-	let draw_state = deserialize_drawable(obj)?;
+fn deserialize_composite(obj: &JsonObject, diag: &mut ParseDiagnostics, path: &str) -> InoxParseResult<Composite> {
+	let draw_state = deserialize_drawable(obj, diag, path)?;
 	Ok(Composite { draw_state })
 }
 
@@ -162,9 +328,9 @@ fn deserialize_simple_physics(obj: &JsonObject) -> InoxParseResult<SimplePhysics
 		param: ParamUuid(obj.get_u32("param")?),
 
 		model_type: match obj.get_str("model_type")? {
-			"Pendulum" => PhysicsModel::RigidPendulum(PhysicsState::default()),
-			"SpringPendulum" => PhysicsModel::SpringPendulum(PhysicsState::default()),
-			a => todo!("{}", a),
+			"Pendulum" => PhysicsModel::RigidPendulum,
+			"SpringPendulum" => PhysicsModel::SpringPendulum,
+			unknown => return Err(InoxParseError::UnknownPhysicsModel(unknown.to_owned())),
 		},
 		map_mode: match obj.get_str("map_mode")? {
 			"AngleLength" => ParamMapMode::AngleLength,
@@ -182,12 +348,10 @@ fn deserialize_simple_physics(obj: &JsonObject) -> InoxParseResult<SimplePhysics
 		},
 
 		local_only: obj.get_bool("local_only").unwrap_or_default(),
-
-		bob: Vec2::ZERO,
 	})
 }
 
-fn deserialize_drawable(obj: &JsonObject) -> InoxParseResult<Drawable> {
+fn deserialize_drawable(obj: &JsonObject, diag: &mut ParseDiagnostics, path: &str) -> InoxParseResult<Drawable> {
 	Ok(Drawable {
 		blend_mode: match obj.get_str("blend_mode")? {
 			"Normal" => BlendMode::Normal,
@@ -202,16 +366,7 @@ fn deserialize_drawable(obj: &JsonObject) -> InoxParseResult<Drawable> {
 		tint: obj.get_vec3("tint").unwrap_or(vec3(1.0, 1.0, 1.0)),
 		screen_tint: obj.get_vec3("screenTint").unwrap_or(vec3(0.0, 0.0, 0.0)),
 		mask_threshold: obj.get_f32("mask_threshold").unwrap_or(0.5),
-		masks: {
-			if let Ok(masks) = obj.get_list("masks") {
-				masks
-					.iter()
-					.filter_map(|mask| deserialize_mask(&JsonObject(mask.as_object()?)).ok())
-					.collect::<Vec<_>>()
-			} else {
-				Vec::new()
-			}
-		},
+		masks: deserialize_masks(obj.get_list("masks").unwrap_or(&[]), diag, &format!("{path}.masks"))?,
 		opacity: obj.get_f32("opacity").unwrap_or(1.0),
 	})
 }
@@ -220,15 +375,22 @@ fn deserialize_mesh(obj: &JsonObject) -> InoxParseResult<Mesh> {
 	Ok(Mesh {
 		vertices: vals("verts", deserialize_vec2s_flat(obj.get_list("verts")?))?,
 		uvs: vals("uvs", deserialize_vec2s_flat(obj.get_list("uvs")?))?,
-		indices: obj
-			.get_list("indices")?
-			.iter()
-			.map_while(JsonValue::as_u16)
-			.collect::<Vec<_>>(),
+		indices: vals("indices", deserialize_u16s(obj.get_list("indices")?))?,
 		origin: obj.get_vec2("origin").unwrap_or_default(),
 	})
 }
 
+fn deserialize_masks(vals: &[JsonValue], diag: &mut ParseDiagnostics, path: &str) -> InoxParseResult<Vec<Mask>> {
+	let mut masks = Vec::with_capacity(vals.len());
+	for (i, mask) in vals.iter().enumerate() {
+		match as_object(mask).and_then(|mask| deserialize_mask(&mask)) {
+			Ok(mask) => masks.push(mask),
+			Err(e) => diag.skip(format!("{path}[{i}]"), e)?,
+		}
+	}
+	Ok(masks)
+}
+
 fn deserialize_mask(obj: &JsonObject) -> InoxParseResult<Mask> {
 	Ok(Mask {
 		source: InoxNodeUuid(obj.get_u32("source")?),
@@ -249,12 +411,26 @@ fn deserialize_transform(obj: &JsonObject) -> InoxParseResult<TransformOffset> {
 	})
 }
 
+fn as_f32(val: &json::JsonValue) -> Option<f32> {
+	val.as_number().map(number_to_f32)
+}
+
 fn deserialize_f32s(val: &[json::JsonValue]) -> Vec<f32> {
-	val.iter().filter_map(JsonValue::as_f32).collect::<Vec<_>>()
+	val.iter().filter_map(as_f32).collect::<Vec<_>>()
+}
+
+fn deserialize_u16s(vals: &[json::JsonValue]) -> InoxParseResult<Vec<u16>> {
+	(vals.iter().enumerate())
+		.map(|(i, val)| match (val.as_number(), val.as_u16()) {
+			(None, _) => Err(JsonError::ElementIsNotNumber.in_list(i).into()),
+			(Some(_), None) => Err(JsonError::ElementOutOfRange.in_list(i).into()),
+			(Some(_), Some(val)) => Ok(val),
+		})
+		.collect()
 }
 
 fn deserialize_vec2s_flat(vals: &[json::JsonValue]) -> InoxParseResult<Vec<Vec2>> {
-	if vals.len() % 2 != 0 {
+	if !vals.len().is_multiple_of(2) {
 		return Err(InoxParseError::OddNumberOfFloatsInList(vals.len()));
 	}
 
@@ -270,8 +446,8 @@ fn deserialize_vec2(vals: &[json::JsonValue]) -> InoxParseResult<Vec2> {
 		return Err(InoxParseError::Not2FloatsInList(vals.len()));
 	}
 
-	let x = vals[0].as_f32().unwrap_or_default();
-	let y = vals[1].as_f32().unwrap_or_default();
+	let x = as_f32(&vals[0]).unwrap_or_default();
+	let y = as_f32(&vals[1]).unwrap_or_default();
 	Ok(vec2(x, y))
 }
 
@@ -289,9 +465,25 @@ pub fn deserialize_puppet(val: &json::JsonValue) -> InoxParseResult<Puppet> {
 	deserialize_puppet_ext(val, &default_deserialize_custom)
 }
 
-pub fn deserialize_puppet_ext<T>(
+pub fn deserialize_puppet_ext<T: CustomNode>(
 	val: &json::JsonValue,
 	deserialize_node_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
+) -> InoxParseResult<Puppet<T>> {
+	let mut diag = ParseDiagnostics::new(ParseMode::Lenient);
+	let puppet = deserialize_puppet_with_diagnostics(val, deserialize_node_custom, &mut diag)?;
+	for warning in diag.warnings() {
+		tracing::warn!("Skipped invalid element: {}", warning);
+	}
+	Ok(puppet)
+}
+
+/// Deserialize a puppet, handling invalid params, bindings and masks according to the mode of `diag`.
+///
+/// In lenient mode, every skipped element is recorded in `diag` with its JSON path.
+pub fn deserialize_puppet_with_diagnostics<T: CustomNode>(
+	val: &json::JsonValue,
+	deserialize_node_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
+	diag: &mut ParseDiagnostics,
 ) -> InoxParseResult<Puppet<T>> {
 	let Some(obj) = val.as_object() else {
 		return Err(InoxParseError::JsonError(JsonError::ValueIsNotObject(
@@ -302,25 +494,93 @@ pub fn deserialize_puppet_ext<T>(
 
 	let nodes = vals(
 		"nodes",
-		deserialize_nodes(&obj.get_object("nodes")?, deserialize_node_custom),
+		deserialize_nodes(&obj.get_object("nodes")?, deserialize_node_custom, diag),
 	)?;
 
 	let meta = vals("meta", deserialize_puppet_meta(&obj.get_object("meta")?))?;
 
 	let physics = vals("physics", deserialize_puppet_physics(&obj.get_object("physics")?))?;
 
-	let parameters = deserialize_params(obj.get_list("param")?);
+	let (parameters, param_groups) = deserialize_params(obj.get_list("param")?, diag)?;
+
+	// Checked before building the render context, which indexes all meshes with u16
+	VertexBuffers::check_len(&nodes)?;
 
-	Ok(Puppet::new(meta, physics, nodes, parameters))
+	// Animations are missing from puppets made before they were introduced
+	let animations = match obj.0.get("animations") {
+		Some(_) => deserialize_animations(&obj.get_object("animations")?, diag)?,
+		None => Vec::new(),
+	};
+
+	let mut data = PuppetData::new(meta, physics, nodes, parameters);
+	data.param_groups = param_groups;
+	data.animations = animations;
+	Ok(Puppet::new(Arc::new(data)))
 }
 
-fn deserialize_params(vals: &[json::JsonValue]) -> HashMap<String, Param> {
-	vals.iter()
-		.map_while(|param| deserialize_param(&JsonObject(param.as_object()?)).ok())
-		.collect()
+/// Parses the list of params, in which groups of params can appear as entries with `children`.
+fn deserialize_params(
+	vals: &[json::JsonValue],
+	diag: &mut ParseDiagnostics,
+) -> InoxParseResult<(HashMap<String, Param>, Vec<ParamGroup>)> {
+	let mut params = HashMap::with_capacity(vals.len());
+	let mut groups = Vec::new();
+	for (i, param) in vals.iter().enumerate() {
+		let path = format!("param[{i}]");
+		let obj = match as_object(param) {
+			Ok(obj) => obj,
+			Err(e) => {
+				diag.skip(path, e)?;
+				continue;
+			}
+		};
+
+		if obj.0.get("children").is_some() {
+			match deserialize_param_group(&obj, &mut params, diag, &path) {
+				Ok(group) => groups.push(group),
+				Err(e) => diag.skip(path, e)?,
+			}
+			continue;
+		}
+
+		match deserialize_param(&obj, diag, &path) {
+			Ok((name, param)) => {
+				params.insert(name, param);
+			}
+			Err(e) => diag.skip(path, e)?,
+		}
+	}
+	Ok((params, groups))
+}
+
+/// Parses a group of params, adding its params to `params`.
+fn deserialize_param_group(
+	obj: &JsonObject,
+	params: &mut HashMap<String, Param>,
+	diag: &mut ParseDiagnostics,
+	path: &str,
+) -> InoxParseResult<ParamGroup> {
+	let mut group = ParamGroup {
+		uuid: obj.get_u32("groupUUID")?,
+		name: obj.get_str("name")?.to_owned(),
+		color: obj.get_vec3("color")?,
+		params: Vec::new(),
+	};
+
+	for (i, param) in obj.get_list("children")?.iter().enumerate() {
+		let path = format!("{path}.children[{i}]");
+		match as_object(param).and_then(|param| deserialize_param(&param, diag, &path)) {
+			Ok((name, param)) => {
+				group.params.push(param.uuid);
+				params.insert(name, param);
+			}
+			Err(e) => diag.skip(path, e)?,
+		}
+	}
+	Ok(group)
 }
 
-fn deserialize_param(obj: &JsonObject) -> InoxParseResult<(String, Param)> {
+fn deserialize_param(obj: &JsonObject, diag: &mut ParseDiagnostics, path: &str) -> InoxParseResult<(String, Param)> {
 	let name = obj.get_str("name")?.to_owned();
 	Ok((
 		name.clone(),
@@ -332,36 +592,55 @@ fn deserialize_param(obj: &JsonObject) -> InoxParseResult<(String, Param)> {
 			max: obj.get_vec2("max")?,
 			defaults: obj.get_vec2("defaults")?,
 			axis_points: vals("axis_points", deserialize_axis_points(obj.get_list("axis_points")?))?,
-			bindings: deserialize_bindings(obj.get_list("bindings")?),
+			bindings: deserialize_bindings(obj.get_list("bindings")?, diag, &format!("{path}.bindings"))?,
 		},
 	))
 }
 
-fn deserialize_bindings(vals: &[json::JsonValue]) -> Vec<Binding> {
-	vals.iter()
-		.filter_map(|binding| deserialize_binding(&JsonObject(binding.as_object()?)).ok())
-		.collect()
+fn deserialize_bindings(
+	vals: &[json::JsonValue],
+	diag: &mut ParseDiagnostics,
+	path: &str,
+) -> InoxParseResult<Vec<Binding>> {
+	let mut bindings = Vec::with_capacity(vals.len());
+	for (i, binding) in vals.iter().enumerate() {
+		match as_object(binding).and_then(|binding| deserialize_binding(&binding)) {
+			Ok(binding) => bindings.push(binding),
+			Err(e) => diag.skip(format!("{path}[{i}]"), e)?,
+		}
+	}
+	Ok(bindings)
 }
 
 fn deserialize_binding(obj: &JsonObject) -> InoxParseResult<Binding> {
-	let is_set = obj
-		.get_list("isSet")?
-		.iter()
-		.map(|bools| bools.members().map_while(JsonValue::as_bool).collect())
-		.collect::<Vec<Vec<_>>>();
+	let is_set = (obj.get_list("isSet")?.iter().enumerate())
+		.map(|(x, bools)| {
+			(as_nested_list(x, bools)?.iter().enumerate())
+				.map(|(y, val)| (val.as_bool()).ok_or_else(|| JsonError::ElementIsNotBool.in_list(y).in_list(x).into()))
+				.collect::<InoxParseResult<Vec<_>>>()
+		})
+		.collect::<InoxParseResult<Vec<_>>>();
+	let is_set = vals("isSet", is_set)?;
 
 	Ok(Binding {
 		node: InoxNodeUuid(obj.get_u32("node")?),
 		is_set: Matrix2d::from_slice_vecs(&is_set, true)?,
-		interpolate_mode: match obj.get_str("interpolate_mode")? {
-			"Linear" => InterpolateMode::Linear,
-			"Nearest" => InterpolateMode::Nearest,
-			a => return Err(InoxParseError::UnknownInterpolateMode(a.to_owned())),
-		},
+		interpolate_mode: deserialize_interpolate_mode(obj.get_str("interpolate_mode")?)?,
 		values: deserialize_binding_values(obj.get_str("param_name")?, obj.get_list("values")?)?,
 	})
 }
 
+fn deserialize_interpolate_mode(mode: &str) -> InoxParseResult<InterpolateMode> {
+	Ok(match mode {
+		"Linear" => InterpolateMode::Linear,
+		"Nearest" => InterpolateMode::Nearest,
+		"Stepped" => InterpolateMode::Stepped,
+		"Cubic" => InterpolateMode::Cubic,
+		"Bezier" => InterpolateMode::Bezier,
+		a => return Err(InoxParseError::UnknownInterpolateMode(a.to_owned())),
+	})
+}
+
 fn deserialize_binding_values(param_name: &str, values: &[JsonValue]) -> InoxParseResult<BindingValues> {
 	Ok(match param_name {
 		"zSort" => BindingValues::ZSort(deserialize_inner_binding_values(values)?),
@@ -372,6 +651,14 @@ fn deserialize_binding_values(param_name: &str, values: &[JsonValue]) -> InoxPar
 		"transform.r.x" => BindingValues::TransformRX(deserialize_inner_binding_values(values)?),
 		"transform.r.y" => BindingValues::TransformRY(deserialize_inner_binding_values(values)?),
 		"transform.r.z" => BindingValues::TransformRZ(deserialize_inner_binding_values(values)?),
+		"opacity" => BindingValues::Opacity(deserialize_inner_binding_values(values)?),
+		"tint.r" => BindingValues::TintR(deserialize_inner_binding_values(values)?),
+		"tint.g" => BindingValues::TintG(deserialize_inner_binding_values(values)?),
+		"tint.b" => BindingValues::TintB(deserialize_inner_binding_values(values)?),
+		"screenTint.r" => BindingValues::ScreenTintR(deserialize_inner_binding_values(values)?),
+		"screenTint.g" => BindingValues::ScreenTintG(deserialize_inner_binding_values(values)?),
+		"screenTint.b" => BindingValues::ScreenTintB(deserialize_inner_binding_values(values)?),
+		"alphaThreshold" => BindingValues::AlphaThreshold(deserialize_inner_binding_values(values)?),
 		"deform" => {
 			let mut parsed = Vec::with_capacity(values.len());
 			for (j, vals) in values.iter().enumerate() {
@@ -400,19 +687,187 @@ fn deserialize_inner_binding_values(values: &[JsonValue]) -> Result<Matrix2d<f32
 }
 
 fn deserialize_axis_points(vals: &[json::JsonValue]) -> InoxParseResult<AxisPoints> {
+	if vals.len() != 2 {
+		return Err(InoxParseError::Not2AxisPointLists(vals.len()));
+	}
+
 	let x = deserialize_f32s(as_nested_list(0, &vals[0])?);
 	let y = deserialize_f32s(as_nested_list(1, &vals[1])?);
 	Ok(AxisPoints { x, y })
 }
 
+/// Parses the animations of a puppet, keyed by name.
+fn deserialize_animations(obj: &JsonObject, diag: &mut ParseDiagnostics) -> InoxParseResult<Vec<Animation>> {
+	let mut animations = Vec::with_capacity(obj.0.len());
+	for (name, animation) in obj.0.iter() {
+		let path = format!("animations.{name}");
+		match as_object(animation).and_then(|animation| deserialize_animation(name, &animation, diag, &path)) {
+			Ok(animation) => animations.push(animation),
+			Err(e) => diag.skip(path, e)?,
+		}
+	}
+	Ok(animations)
+}
+
+fn deserialize_animation(
+	name: &str,
+	obj: &JsonObject,
+	diag: &mut ParseDiagnostics,
+	path: &str,
+) -> InoxParseResult<Animation> {
+	let mut lanes = Vec::new();
+	for (i, lane) in obj.get_list("lanes")?.iter().enumerate() {
+		match as_object(lane).and_then(|lane| deserialize_animation_lane(&lane)) {
+			Ok(lane) => lanes.push(lane),
+			Err(e) => diag.skip(format!("{path}.lanes[{i}]"), e)?,
+		}
+	}
+
+	Ok(Animation {
+		name: name.to_owned(),
+		timestep: obj.get_f32("timestep")?,
+		additive: obj.get_bool("additive")?,
+		weight: obj.get_f32("animationWeight")?,
+		length: obj.get_i32("length")?,
+		lead_in: obj.get_i32("leadIn")?,
+		lead_out: obj.get_i32("leadOut")?,
+		lanes,
+	})
+}
+
+fn deserialize_animation_lane(obj: &JsonObject) -> InoxParseResult<AnimationLane> {
+	let mut keyframes = Vec::new();
+	for (i, keyframe) in obj.get_list("keyframes")?.iter().enumerate() {
+		let keyframe = as_object(keyframe)
+			.and_then(|keyframe| {
+				Ok(Keyframe {
+					frame: keyframe.get_i32("frame")?,
+					value: keyframe.get_f32("value")?,
+					tension: keyframe.get_f32("tension")?,
+				})
+			})
+			.map_err(|e| match e {
+				InoxParseError::JsonError(e) => InoxParseError::JsonError(e.in_list(i).nested("keyframes")),
+				e => e,
+			})?;
+		keyframes.push(keyframe);
+	}
+	keyframes.sort_by_key(|keyframe| keyframe.frame);
+
+	Ok(AnimationLane {
+		param: ParamUuid(obj.get_u32("uuid")?),
+		target: obj.get_usize("target")?,
+		interpolation: deserialize_interpolate_mode(obj.get_str("interpolation")?)?,
+		merge_mode: match obj.get_str("merge_mode")? {
+			"Forced" => ParamMergeMode::Forced,
+			"Additive" => ParamMergeMode::Additive,
+			"Multiplicative" => ParamMergeMode::Multiplicative,
+			"Weighted" => ParamMergeMode::Weighted,
+			a => return Err(InoxParseError::UnknownMergeMode(a.to_owned())),
+		},
+		keyframes,
+	})
+}
+
+/// Loads the automations stored in the vendor data of a model into its freshly parsed puppet.
+///
+/// They are kept out of the puppet payload, whose `automation` key belongs to Inochi2D.
+pub(crate) fn deserialize_vendor_automations<T>(
+	puppet: &mut Puppet<T>,
+	vendors: &[VendorData],
+	diag: &mut ParseDiagnostics,
+) -> InoxParseResult<()> {
+	let Some(vendor) = vendors.iter().find(|vendor| vendor.name == AUTOMATIONS_VENDOR_NAME) else {
+		return Ok(());
+	};
+	let JsonValue::Array(ref vals) = vendor.payload else {
+		return Err(InoxParseError::JsonError(JsonError::ValueIsNotList(
+			AUTOMATIONS_VENDOR_NAME.to_owned(),
+		)));
+	};
+
+	let mut automations = Vec::with_capacity(vals.len());
+	for (i, automation) in vals.iter().enumerate() {
+		match as_object(automation).and_then(|automation| deserialize_automation(&automation)) {
+			Ok(automation) => automations.push(automation),
+			Err(e) => diag.skip(format!("{AUTOMATIONS_VENDOR_NAME}[{i}]"), e)?,
+		}
+	}
+	// A freshly parsed puppet is not shared with other instances yet
+	if let Some(data) = Arc::get_mut(&mut puppet.data) {
+		data.automations = automations;
+	}
+	Ok(())
+}
+
+fn deserialize_automation(obj: &JsonObject) -> InoxParseResult<Automation> {
+	let generator = match obj.get_str("type")? {
+		"wave" => Generator::Wave {
+			shape: match obj.get_str("shape")? {
+				"Sine" => WaveShape::Sine,
+				"Triangle" => WaveShape::Triangle,
+				"Square" => WaveShape::Square,
+				"Sawtooth" => WaveShape::Sawtooth,
+				a => return Err(InoxParseError::UnknownWaveShape(a.to_owned())),
+			},
+			period: obj.get_f32("period")?,
+			phase: obj.get_f32("phase")?,
+		},
+		"noise" => Generator::Noise {
+			frequency: obj.get_f32("frequency")?,
+		},
+		"blink" => {
+			let (interval, variance, duration) = (
+				obj.get_f32("interval")?,
+				obj.get_f32("variance")?,
+				obj.get_f32("duration")?,
+			);
+			// Blinks would otherwise never end, or never be scheduled past the current one
+			if !(interval > 0.0 && duration > 0.0 && (0.0..1.0).contains(&variance)) {
+				return Err(InoxParseError::InvalidBlink {
+					interval,
+					variance,
+					duration,
+				});
+			}
+			Generator::Blink {
+				interval,
+				variance,
+				duration,
+			}
+		}
+		a => return Err(InoxParseError::UnknownAutomationType(a.to_owned())),
+	};
+
+	let mut bindings = Vec::new();
+	for (i, binding) in obj.get_list("bindings")?.iter().enumerate() {
+		let binding = as_object(binding)
+			.and_then(|binding| {
+				Ok(AutomationBinding {
+					param: ParamUuid(binding.get_u32("param")?),
+					axis: binding.get_usize("axis")?,
+					range: binding.get_vec2("range")?,
+				})
+			})
+			.map_err(|e| match e {
+				InoxParseError::JsonError(e) => InoxParseError::JsonError(e.in_list(i).nested("bindings")),
+				e => e,
+			})?;
+		bindings.push(binding);
+	}
+
+	Ok(Automation::new(obj.get_str("name")?, generator, bindings))
+}
+
 fn deserialize_nodes<T>(
 	obj: &JsonObject,
 	deserialize_node_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
+	diag: &mut ParseDiagnostics,
 ) -> InoxParseResult<InoxNodeTree<T>> {
 	let mut arena = Arena::new();
 	let mut uuids = HashMap::new();
 
-	let root_node = deserialize_node(obj, deserialize_node_custom)?;
+	let root_node = deserialize_node(obj, deserialize_node_custom, diag, "nodes")?;
 	let root_uuid = root_node.uuid;
 	let root = arena.new_node(root_node);
 	uuids.insert(root_uuid, root);
@@ -426,7 +881,8 @@ fn deserialize_nodes<T>(
 			))));
 		};
 
-		let child_id = deserialize_nodes_rec(&JsonObject(child), deserialize_node_custom, &mut node_tree)
+		let path = format!("nodes.children[{i}]");
+		let child_id = deserialize_nodes_rec(&JsonObject(child), deserialize_node_custom, &mut node_tree, diag, &path)
 			.map_err(|e| e.nested(&format!("children[{i}]")))?;
 
 		root.append(child_id, &mut node_tree.arena);
@@ -439,8 +895,10 @@ fn deserialize_nodes_rec<T>(
 	obj: &JsonObject,
 	deserialize_node_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
 	node_tree: &mut InoxNodeTree<T>,
+	diag: &mut ParseDiagnostics,
+	path: &str,
 ) -> InoxParseResult<indextree::NodeId> {
-	let node = deserialize_node(obj, deserialize_node_custom)?;
+	let node = deserialize_node(obj, deserialize_node_custom, diag, path)?;
 	let uuid = node.uuid;
 	let node_id = node_tree.arena.new_node(node);
 	node_tree.uuids.insert(uuid, node_id);
@@ -451,8 +909,15 @@ fn deserialize_nodes_rec<T>(
 				"children[{i}]"
 			))));
 		};
-		let child_id = deserialize_nodes_rec(&JsonObject(child), deserialize_node_custom, node_tree)
-			.map_err(|e| e.nested(&format!("children[{i}]")))?;
+		let child_path = format!("{path}.children[{i}]");
+		let child_id = deserialize_nodes_rec(
+			&JsonObject(child),
+			deserialize_node_custom,
+			node_tree,
+			diag,
+			&child_path,
+		)
+		.map_err(|e| e.nested(&format!("children[{i}]")))?;
 
 		node_id.append(child_id, &mut node_tree.arena);
 	}
@@ -512,3 +977,789 @@ fn deserialize_puppet_usage_rights(obj: &JsonObject) -> InoxParseResult<PuppetUs
 		require_attribution: obj.get_bool("require_attribution")?,
 	})
 }
+
+// Puppet serialization
+
+fn default_serialize_custom<T>(_data: &T, _obj: &mut json::object::Object) -> String {
+	// Without knowledge of the custom data, the best we can do is to keep it as a plain node
+	"Node".to_owned()
+}
+
+fn serialize_node<T>(
+	node: &InoxNode<T>,
+	serialize_node_custom: &impl Fn(&T, &mut json::object::Object) -> String,
+) -> json::object::Object {
+	let mut data = json::object::Object::new();
+	let node_type = serialize_node_data(&node.data, &mut data, serialize_node_custom);
+
+	let mut obj = json::object::Object::new();
+	obj.insert("uuid", node.uuid.0.into());
+	obj.insert("name", node.name.as_str().into());
+	obj.insert("type", node_type.into());
+	obj.insert("enabled", node.enabled.into());
+	obj.insert("zsort", node.zsort.into());
+	obj.insert("transform", serialize_transform(&node.trans_offset));
+	obj.insert("lockToRoot", node.lock_to_root.into());
+	for (key, value) in data.iter() {
+		obj.insert(key, value.clone());
+	}
+	obj
+}
+
+fn serialize_node_data<T>(
+	data: &InoxData<T>,
+	obj: &mut json::object::Object,
+	serialize_custom: &impl Fn(&T, &mut json::object::Object) -> String,
+) -> String {
+	match data {
+		InoxData::Node => "Node".to_owned(),
+		InoxData::Part(part) => {
+			serialize_part(part, obj);
+			"Part".to_owned()
+		}
+		InoxData::Composite(composite) => {
+			serialize_composite(composite, obj);
+			"Composite".to_owned()
+		}
+		InoxData::SimplePhysics(simple_physics) => {
+			serialize_simple_physics(simple_physics, obj);
+			"SimplePhysics".to_owned()
+		}
+		InoxData::Custom(custom) => (serialize_custom)(custom, obj),
+	}
+}
+
+fn serialize_part(part: &Part, obj: &mut json::object::Object) {
+	// The parser maps u32::MAX (no texture) to texture 0 for emissive and bumpmap textures, map it back
+	let optional_texture = |id: TextureId| if id.0 == 0 { u32::MAX as usize } else { id.0 };
+
+	obj.insert(
+		"textures",
+		json::array![
+			part.tex_albedo.0,
+			optional_texture(part.tex_emissive),
+			optional_texture(part.tex_bumpmap)
+		],
+	);
+	obj.insert("mesh", serialize_mesh(&part.mesh));
+	serialize_drawable(&part.draw_state, obj);
+}
+
+fn serialize_composite(composite: &Composite, obj: &mut json::object::Object) {
+	serialize_drawable(&composite.draw_state, obj);
+}
+
+fn serialize_simple_physics(simple_physics: &SimplePhysics, obj: &mut json::object::Object) {
+	let props = &simple_physics.props;
+
+	obj.insert("param", simple_physics.param.0.into());
+	obj.insert(
+		"model_type",
+		match simple_physics.model_type {
+			PhysicsModel::RigidPendulum => "Pendulum",
+			PhysicsModel::SpringPendulum => "SpringPendulum",
+		}
+		.into(),
+	);
+	obj.insert(
+		"map_mode",
+		match simple_physics.map_mode {
+			ParamMapMode::AngleLength => "AngleLength",
+			ParamMapMode::XY => "XY",
+		}
+		.into(),
+	);
+	obj.insert("gravity", props.gravity.into());
+	obj.insert("length", props.length.into());
+	obj.insert("frequency", props.frequency.into());
+	obj.insert("angle_damping", props.angle_damping.into());
+	obj.insert("length_damping", props.length_damping.into());
+	obj.insert("output_scale", serialize_vec2(props.output_scale));
+	obj.insert("local_only", simple_physics.local_only.into());
+}
+
+fn serialize_drawable(drawable: &Drawable, obj: &mut json::object::Object) {
+	obj.insert(
+		"blend_mode",
+		match drawable.blend_mode {
+			BlendMode::Normal => "Normal",
+			BlendMode::Multiply => "Multiply",
+			BlendMode::ColorDodge => "ColorDodge",
+			BlendMode::LinearDodge => "LinearDodge",
+			BlendMode::Screen => "Screen",
+			BlendMode::ClipToLower => "ClipToLower",
+			BlendMode::SliceFromLower => "SliceFromLower",
+		}
+		.into(),
+	);
+	obj.insert("tint", serialize_vec3(drawable.tint));
+	obj.insert("screenTint", serialize_vec3(drawable.screen_tint));
+	obj.insert("mask_threshold", drawable.mask_threshold.into());
+	obj.insert(
+		"masks",
+		JsonValue::Array(drawable.masks.iter().map(serialize_mask).collect()),
+	);
+	obj.insert("opacity", drawable.opacity.into());
+}
+
+fn serialize_mesh(mesh: &Mesh) -> JsonValue {
+	json::object! {
+		"verts": serialize_vec2s_flat(&mesh.vertices),
+		"uvs": serialize_vec2s_flat(&mesh.uvs),
+		"indices": mesh.indices.clone(),
+		"origin": serialize_vec2(mesh.origin),
+	}
+}
+
+fn serialize_mask(mask: &Mask) -> JsonValue {
+	json::object! {
+		"source": mask.source.0,
+		"mode": match mask.mode {
+			MaskMode::Mask => "Mask",
+			MaskMode::Dodge => "DodgeMask",
+		},
+	}
+}
+
+fn serialize_transform(transform: &TransformOffset) -> JsonValue {
+	json::object! {
+		"trans": serialize_vec3(transform.translation),
+		"rot": serialize_vec3(transform.rotation),
+		"scale": serialize_vec2(transform.scale),
+		"pixel_snap": transform.pixel_snap,
+	}
+}
+
+fn serialize_vec2s_flat(vec2s: &[Vec2]) -> JsonValue {
+	JsonValue::Array(vec2s.iter().flat_map(|v| [v.x.into(), v.y.into()]).collect())
+}
+
+fn serialize_vec2(vec2: Vec2) -> JsonValue {
+	json::array![vec2.x, vec2.y]
+}
+
+fn serialize_vec3(vec3: Vec3) -> JsonValue {
+	json::array![vec3.x, vec3.y, vec3.z]
+}
+
+/// Serializes a puppet into the JSON payload of the Inochi2D spec.
+///
+/// This is the inverse of [`deserialize_puppet`].
+pub fn serialize_puppet(puppet: &Puppet) -> JsonValue {
+	serialize_puppet_ext(puppet, &default_serialize_custom)
+}
+
+/// Serializes a puppet with custom nodes into the JSON payload of the Inochi2D spec.
+///
+/// `serialize_node_custom` writes the fields of a custom node into its JSON object and returns its node type.
+/// This is the inverse of [`deserialize_puppet_ext`].
+pub fn serialize_puppet_ext<T>(
+	puppet: &Puppet<T>,
+	serialize_node_custom: &impl Fn(&T, &mut json::object::Object) -> String,
+) -> JsonValue {
+	json::object! {
+		"meta": serialize_puppet_meta(&puppet.meta),
+		"physics": serialize_puppet_physics(&puppet.physics),
+		"nodes": serialize_nodes(&puppet.nodes, serialize_node_custom),
+		"param": serialize_params(puppet),
+		"animations": serialize_animations(&puppet.animations),
+	}
+}
+
+/// Vendor data of a model with the automations of its puppet, see [`deserialize_vendor_automations`].
+///
+/// Any previous automation data is replaced, and dropped if the puppet has no automations.
+pub(crate) fn serialize_vendor_automations<T>(puppet: &Puppet<T>, vendors: &[VendorData]) -> Vec<VendorData> {
+	let mut vendors = (vendors.iter())
+		.filter(|vendor| vendor.name != AUTOMATIONS_VENDOR_NAME)
+		.cloned()
+		.collect::<Vec<_>>();
+	if !puppet.automations.is_empty() {
+		vendors.push(VendorData {
+			name: AUTOMATIONS_VENDOR_NAME.to_owned(),
+			payload: JsonValue::Array(puppet.automations.iter().map(serialize_automation).collect()),
+		});
+	}
+	vendors
+}
+
+fn serialize_params<T>(puppet: &Puppet<T>) -> JsonValue {
+	let mut vals = Vec::with_capacity(puppet.params.len());
+
+	// Groups come first, followed by the params outside of any group
+	let mut grouped = HashSet::new();
+	for group in &puppet.param_groups {
+		let children = (group.params.iter())
+			.filter_map(|uuid| puppet.params.get(uuid))
+			.map(serialize_param)
+			.collect::<Vec<_>>();
+		grouped.extend(group.params.iter().copied());
+
+		vals.push(json::object! {
+			"groupUUID": group.uuid,
+			"name": group.name.as_str(),
+			"color": serialize_vec3(group.color),
+			"children": JsonValue::Array(children),
+		});
+	}
+
+	// Parameters are stored in a hashmap, sort them to get a deterministic output
+	let mut params = (puppet.params.values())
+		.filter(|param| !grouped.contains(&param.uuid))
+		.collect::<Vec<_>>();
+	params.sort_by_key(|param| param.uuid);
+	vals.extend(params.into_iter().map(serialize_param));
+
+	JsonValue::Array(vals)
+}
+
+fn serialize_param(param: &Param) -> JsonValue {
+	json::object! {
+		"uuid": param.uuid.0,
+		"name": param.name.as_str(),
+		"is_vec2": param.is_vec2,
+		"min": serialize_vec2(param.min),
+		"max": serialize_vec2(param.max),
+		"defaults": serialize_vec2(param.defaults),
+		"axis_points": serialize_axis_points(&param.axis_points),
+		"bindings": JsonValue::Array(param.bindings.iter().map(serialize_binding).collect()),
+	}
+}
+
+fn serialize_binding(binding: &Binding) -> JsonValue {
+	json::object! {
+		"node": binding.node.0,
+		"param_name": binding.values.param_name(),
+		"values": serialize_binding_values(&binding.values),
+		"isSet": binding.is_set.to_slice_vecs(),
+		"interpolate_mode": serialize_interpolate_mode(binding.interpolate_mode),
+	}
+}
+
+fn serialize_interpolate_mode(mode: InterpolateMode) -> &'static str {
+	match mode {
+		InterpolateMode::Linear => "Linear",
+		InterpolateMode::Nearest => "Nearest",
+		InterpolateMode::Stepped => "Stepped",
+		InterpolateMode::Cubic => "Cubic",
+		InterpolateMode::Bezier => "Bezier",
+	}
+}
+
+fn serialize_animations(animations: &[Animation]) -> JsonValue {
+	let mut obj = json::object::Object::with_capacity(animations.len());
+	for animation in animations {
+		obj.insert(&animation.name, serialize_animation(animation));
+	}
+	JsonValue::Object(obj)
+}
+
+fn serialize_animation(animation: &Animation) -> JsonValue {
+	json::object! {
+		"timestep": animation.timestep,
+		"additive": animation.additive,
+		"animationWeight": animation.weight,
+		"length": animation.length,
+		"leadIn": animation.lead_in,
+		"leadOut": animation.lead_out,
+		"lanes": JsonValue::Array(animation.lanes.iter().map(serialize_animation_lane).collect()),
+	}
+}
+
+fn serialize_animation_lane(lane: &AnimationLane) -> JsonValue {
+	let keyframes = (lane.keyframes.iter())
+		.map(|keyframe| {
+			json::object! {
+				"frame": keyframe.frame,
+				"value": keyframe.value,
+				"tension": keyframe.tension,
+			}
+		})
+		.collect::<Vec<_>>();
+
+	json::object! {
+		"uuid": lane.param.0,
+		"target": lane.target,
+		"interpolation": serialize_interpolate_mode(lane.interpolation),
+		"merge_mode": match lane.merge_mode {
+			ParamMergeMode::Forced => "Forced",
+			ParamMergeMode::Additive => "Additive",
+			ParamMergeMode::Multiplicative => "Multiplicative",
+			ParamMergeMode::Weighted => "Weighted",
+		},
+		"keyframes": keyframes,
+	}
+}
+
+fn serialize_automation(automation: &Automation) -> JsonValue {
+	let bindings = (automation.bindings.iter())
+		.map(|binding| {
+			json::object! {
+				"param": binding.param.0,
+				"axis": binding.axis,
+				"range": serialize_vec2(binding.range),
+			}
+		})
+		.collect::<Vec<_>>();
+
+	let mut obj = json::object! {
+		"name": automation.name.as_str(),
+		"bindings": bindings,
+	};
+	match automation.generator {
+		Generator::Wave { shape, period, phase } => {
+			obj["type"] = "wave".into();
+			obj["shape"] = match shape {
+				WaveShape::Sine => "Sine",
+				WaveShape::Triangle => "Triangle",
+				WaveShape::Square => "Square",
+				WaveShape::Sawtooth => "Sawtooth",
+			}
+			.into();
+			obj["period"] = period.into();
+			obj["phase"] = phase.into();
+		}
+		Generator::Noise { frequency } => {
+			obj["type"] = "noise".into();
+			obj["frequency"] = frequency.into();
+		}
+		Generator::Blink {
+			interval,
+			variance,
+			duration,
+		} => {
+			obj["type"] = "blink".into();
+			obj["interval"] = interval.into();
+			obj["variance"] = variance.into();
+			obj["duration"] = duration.into();
+		}
+	}
+	obj
+}
+
+fn serialize_binding_values(values: &BindingValues) -> JsonValue {
+	match values {
+		BindingValues::ZSort(matrix)
+		| BindingValues::TransformTX(matrix)
+		| BindingValues::TransformTY(matrix)
+		| BindingValues::TransformSX(matrix)
+		| BindingValues::TransformSY(matrix)
+		| BindingValues::TransformRX(matrix)
+		| BindingValues::TransformRY(matrix)
+		| BindingValues::TransformRZ(matrix)
+		| BindingValues::Opacity(matrix)
+		| BindingValues::TintR(matrix)
+		| BindingValues::TintG(matrix)
+		| BindingValues::TintB(matrix)
+		| BindingValues::ScreenTintR(matrix)
+		| BindingValues::ScreenTintG(matrix)
+		| BindingValues::ScreenTintB(matrix)
+		| BindingValues::AlphaThreshold(matrix) => matrix.to_slice_vecs().into(),
+		BindingValues::Deform(matrix) => {
+			let values = (matrix.to_slice_vecs().iter())
+				.map(|line| {
+					(line.iter())
+						.map(|vec2s| JsonValue::Array(vec2s.iter().copied().map(serialize_vec2).collect()))
+						.collect()
+				})
+				.map(JsonValue::Array)
+				.collect();
+
+			JsonValue::Array(values)
+		}
+	}
+}
+
+fn serialize_axis_points(axis_points: &AxisPoints) -> JsonValue {
+	json::array![axis_points.x.clone(), axis_points.y.clone()]
+}
+
+fn serialize_nodes<T>(
+	nodes: &InoxNodeTree<T>,
+	serialize_node_custom: &impl Fn(&T, &mut json::object::Object) -> String,
+) -> JsonValue {
+	serialize_nodes_rec(nodes, nodes.root, serialize_node_custom)
+}
+
+fn serialize_nodes_rec<T>(
+	nodes: &InoxNodeTree<T>,
+	node_id: indextree::NodeId,
+	serialize_node_custom: &impl Fn(&T, &mut json::object::Object) -> String,
+) -> JsonValue {
+	let mut obj = serialize_node(nodes.arena[node_id].get(), serialize_node_custom);
+
+	let children = (node_id.children(&nodes.arena))
+		.map(|child_id| serialize_nodes_rec(nodes, child_id, serialize_node_custom))
+		.collect();
+	obj.insert("children", JsonValue::Array(children));
+
+	JsonValue::Object(obj)
+}
+
+fn serialize_puppet_physics(physics: &PuppetPhysics) -> JsonValue {
+	json::object! {
+		"pixelsPerMeter": physics.pixels_per_meter,
+		"gravity": physics.gravity,
+	}
+}
+
+fn serialize_puppet_meta(meta: &PuppetMeta) -> JsonValue {
+	let mut obj = json::object::Object::new();
+	obj.insert("name", meta.name.as_deref().into());
+	obj.insert("version", meta.version.as_str().into());
+	obj.insert("rigger", meta.rigger.as_deref().into());
+	obj.insert("artist", meta.artist.as_deref().into());
+	if let Some(ref rights) = meta.rights {
+		obj.insert("rights", serialize_puppet_usage_rights(rights));
+	}
+	obj.insert("copyright", meta.copyright.as_deref().into());
+	obj.insert("licenseURL", meta.license_url.as_deref().into());
+	obj.insert("contact", meta.contact.as_deref().into());
+	obj.insert("reference", meta.reference.as_deref().into());
+	if let Some(thumbnail_id) = meta.thumbnail_id {
+		obj.insert("thumbnailId", thumbnail_id.into());
+	}
+	obj.insert("preservePixels", meta.preserve_pixels.into());
+	JsonValue::Object(obj)
+}
+
+fn serialize_puppet_usage_rights(rights: &PuppetUsageRights) -> JsonValue {
+	json::object! {
+		"allowed_users": match rights.allowed_users {
+			PuppetAllowedUsers::OnlyAuthor => "OnlyAuthor",
+			PuppetAllowedUsers::OnlyLicensee => "OnlyLicensee",
+			PuppetAllowedUsers::Everyone => "Everyone",
+		},
+		"allow_violence": rights.allow_violence,
+		"allow_sexual": rights.allow_sexual,
+		"allow_commercial": rights.allow_commercial,
+		"allow_redistribution": match rights.allow_redistribution {
+			PuppetAllowedRedistribution::Prohibited => "Prohibited",
+			PuppetAllowedRedistribution::ViralLicense => "ViralLicense",
+			PuppetAllowedRedistribution::CopyleftLicense => "CopyleftLicense",
+		},
+		"allow_modification": match rights.allow_modification {
+			PuppetAllowedModification::Prohibited => "Prohibited",
+			PuppetAllowedModification::AllowPersonal => "AllowPersonal",
+			PuppetAllowedModification::AllowRedistribute => "AllowRedistribute",
+		},
+		"require_attribution": rights.require_attribution,
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+
+	/// JSON payload of the test fixture puppet.
+	pub(crate) fn test_payload() -> JsonValue {
+		json::parse(include_str!("../../tests/fixtures/puppet.json")).unwrap()
+	}
+
+	/// Test fixture puppet, shared by the tests of all modules.
+	pub(crate) fn test_puppet() -> Puppet {
+		deserialize_puppet(&test_payload()).unwrap()
+	}
+
+	/// Checks that every key of `expected` is also present in `actual`, recursively.
+	fn assert_keys_preserved(expected: &JsonValue, actual: &JsonValue, path: &str) {
+		match (expected, actual) {
+			(JsonValue::Object(expected), JsonValue::Object(actual)) => {
+				for (key, value) in expected.iter() {
+					let path = format!("{path}.{key}");
+					let Some(actual) = actual.get(key) else {
+						panic!("key {path} was not serialized");
+					};
+					assert_keys_preserved(value, actual, &path);
+				}
+			}
+			(JsonValue::Array(expected), JsonValue::Array(actual)) if expected.len() == actual.len() => {
+				for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+					assert_keys_preserved(expected, actual, &format!("{path}[{i}]"));
+				}
+			}
+			(JsonValue::Array(_), JsonValue::Array(_)) => {
+				// Lists of different lengths are fine as long as they are not lists of objects
+				assert!(
+					!expected.members().any(JsonValue::is_object),
+					"list {path} changed length"
+				);
+			}
+			(expected, actual) => assert_eq!(expected.is_null(), actual.is_null(), "value {path} changed type"),
+		}
+	}
+
+	#[test]
+	fn test_serialize_roundtrip() {
+		let payload = test_payload();
+		let puppet = deserialize_puppet(&payload).unwrap();
+		let serialized = serialize_puppet(&puppet);
+
+		let reparsed = deserialize_puppet(&serialized).unwrap();
+		assert_eq!(serialized.dump(), serialize_puppet(&reparsed).dump());
+
+		// Params are sorted by uuid, just like in the fixture
+		assert_keys_preserved(&payload, &serialized, "(puppet)");
+	}
+
+	#[test]
+	fn test_serialize_values() {
+		let puppet = test_puppet();
+		let reparsed = deserialize_puppet(&serialize_puppet(&puppet)).unwrap();
+
+		assert_eq!(reparsed.meta.name.as_deref(), Some("Test Puppet"));
+		assert_eq!(reparsed.meta.rigger, None);
+		assert!(reparsed.meta.rights.is_some());
+		assert_eq!(reparsed.physics.gravity, 9.8);
+
+		let hair = reparsed.nodes.get_node(InoxNodeUuid(3)).unwrap();
+		assert_eq!(hair.zsort, -0.2);
+		let InoxData::Part(ref part) = hair.data else {
+			panic!("Hair is not a part");
+		};
+		assert_eq!(part.tex_emissive, TextureId(2));
+		assert_eq!(part.tex_bumpmap, TextureId(0));
+		assert_eq!(part.draw_state.blend_mode, BlendMode::Multiply);
+		assert_eq!(
+			part.draw_state.masks,
+			vec![Mask {
+				source: InoxNodeUuid(2),
+				mode: MaskMode::Mask
+			}]
+		);
+		assert_eq!(part.mesh.indices, vec![0, 1, 2]);
+
+		let param = reparsed.get_named_param("Head:: Yaw-Pitch").unwrap();
+		assert_eq!(param.axis_points.x, vec![0.0, 0.5, 1.0]);
+		let BindingValues::Deform(ref deform) = param.bindings[1].values else {
+			panic!("second binding is not a deform");
+		};
+		assert_eq!(deform[(2, 1)], vec![vec2(1.0, 1.0); 3]);
+		assert_eq!(deform[(0, 1)], vec![vec2(0.0, 1.0); 3]);
+	}
+
+	/// Fixture with an invalid binding, an invalid mask and a param that is not an object.
+	fn broken_payload() -> JsonValue {
+		let mut payload = test_payload();
+		payload["param"][0]["bindings"][0].remove("node");
+		payload["nodes"]["children"][0]["children"][0]["masks"][0]["mode"] = "Unknown".into();
+		payload["param"].push(42).unwrap();
+		payload
+	}
+
+	#[test]
+	fn test_lenient_diagnostics() {
+		let mut diag = ParseDiagnostics::new(ParseMode::Lenient);
+		let puppet =
+			deserialize_puppet_with_diagnostics(&broken_payload(), &default_deserialize_custom::<()>, &mut diag)
+				.unwrap();
+
+		assert_eq!(puppet.params.len(), 2);
+		assert_eq!(puppet.get_named_param("Head:: Yaw-Pitch").unwrap().bindings.len(), 1);
+
+		let paths = diag.warnings().iter().map(|w| w.path.as_str()).collect::<Vec<_>>();
+		assert_eq!(
+			paths,
+			vec![
+				"nodes.children[0].children[0].masks[0]",
+				"param[0].bindings[0].node",
+				"param[2]"
+			]
+		);
+		assert!(matches!(
+			diag.warnings()[0].error,
+			InoxParseError::UnknownMaskMode(ref mode) if mode == "Unknown"
+		));
+	}
+
+	#[test]
+	fn test_strict_diagnostics() {
+		let mut payload = broken_payload();
+		payload["nodes"]["children"][0]["children"][0]["masks"][0]["mode"] = "Mask".into();
+
+		let mut diag = ParseDiagnostics::new(ParseMode::Strict);
+		let err =
+			deserialize_puppet_with_diagnostics(&payload, &default_deserialize_custom::<()>, &mut diag).unwrap_err();
+		let InoxParseError::InvalidElement(warning) = err else {
+			panic!("unexpected error {err}");
+		};
+		assert_eq!(warning.path, "param[0].bindings[0].node");
+		assert!(diag.warnings().is_empty());
+	}
+
+	#[test]
+	fn test_invalid_list_elements() {
+		let mut payload = test_payload();
+		let hair = &mut payload["nodes"]["children"][0]["children"][0];
+		assert_eq!(hair["name"], "Hair");
+		hair["mesh"]["indices"] = json::array![0, 1, 70000];
+		let err = deserialize_puppet(&payload).unwrap_err();
+		let InoxParseError::JsonError(ref error) = err else {
+			panic!("unexpected error {err}");
+		};
+		assert_eq!(error.path(), "nodes.children[0].children[0].data.mesh.indices[2]");
+
+		payload["nodes"]["children"][0]["children"][0]["mesh"]["indices"] = json::array![0, "1"];
+		assert!(deserialize_puppet(&payload).is_err());
+
+		let mut payload = test_payload();
+		payload["param"][1]["bindings"][0]["isSet"][1][0] = 1.into();
+		let mut diag = ParseDiagnostics::new(ParseMode::Lenient);
+		let puppet =
+			deserialize_puppet_with_diagnostics(&payload, &default_deserialize_custom::<()>, &mut diag).unwrap();
+		let paths = diag.warnings().iter().map(|w| w.path.as_str()).collect::<Vec<_>>();
+		assert_eq!(paths, vec!["param[1].bindings[0].isSet[1][0]"]);
+		assert!(puppet.get_param(ParamUuid(11)).unwrap().bindings.is_empty());
+	}
+
+	#[test]
+	fn test_param_groups() {
+		// Move the second param into a group, with an invalid child
+		let mut payload = test_payload();
+		let hair_physics = payload["param"].array_remove(1);
+		payload["param"]
+			.push(json::object! {
+				"groupUUID": 20,
+				"name": "Hair",
+				"color": [1, 0.5, 0],
+				"children": [hair_physics, 42],
+			})
+			.unwrap();
+
+		let mut diag = ParseDiagnostics::new(ParseMode::Lenient);
+		let puppet =
+			deserialize_puppet_with_diagnostics(&payload, &default_deserialize_custom::<()>, &mut diag).unwrap();
+		assert_eq!(puppet.params.len(), 2);
+		assert_eq!(
+			puppet.param_groups,
+			vec![ParamGroup {
+				uuid: 20,
+				name: "Hair".to_owned(),
+				color: vec3(1.0, 0.5, 0.0),
+				params: vec![ParamUuid(11)],
+			}]
+		);
+		let paths = diag.warnings().iter().map(|w| w.path.as_str()).collect::<Vec<_>>();
+		assert_eq!(paths, vec!["param[1].children[1]"]);
+
+		let serialized = serialize_puppet(&puppet);
+		assert_eq!(serialized["param"][0]["groupUUID"], 20);
+		assert_eq!(serialized["param"][0]["children"][0]["uuid"], 11);
+		assert_eq!(serialized["param"][1]["uuid"], 10);
+		assert_eq!(
+			deserialize_puppet(&serialized).unwrap().param_groups,
+			puppet.param_groups
+		);
+	}
+
+	#[test]
+	fn test_animations() {
+		let mut payload = test_payload();
+		payload["animations"] = json::object! {
+			"idle": {
+				"timestep": 0.5,
+				"additive": false,
+				"animationWeight": 1,
+				"length": 4,
+				"leadIn": -1,
+				"leadOut": -1,
+				"lanes": [
+					{
+						"uuid": 10,
+						"target": 1,
+						"interpolation": "Cubic",
+						"merge_mode": "Additive",
+						"keyframes": [
+							{ "frame": 4, "value": 0, "tension": 0.5 },
+							{ "frame": 0, "value": 1, "tension": 0.5 },
+						],
+					},
+					{
+						"uuid": 10,
+						"target": 0,
+						"interpolation": "Linear",
+						"merge_mode": "Passthrough",
+						"keyframes": [],
+					},
+				],
+			},
+		};
+
+		let mut diag = ParseDiagnostics::new(ParseMode::Lenient);
+		let puppet =
+			deserialize_puppet_with_diagnostics(&payload, &default_deserialize_custom::<()>, &mut diag).unwrap();
+		let paths = diag.warnings().iter().map(|w| w.path.as_str()).collect::<Vec<_>>();
+		assert_eq!(paths, vec!["animations.idle.lanes[1]"]);
+
+		let idle = puppet.get_animation("idle").unwrap();
+		assert_eq!(idle.timestep, 0.5);
+		assert_eq!(idle.lanes.len(), 1);
+		assert_eq!(idle.lanes[0].target, 1);
+		assert_eq!(idle.lanes[0].interpolation, InterpolateMode::Cubic);
+		assert_eq!(idle.lanes[0].merge_mode, ParamMergeMode::Additive);
+		assert_eq!(idle.lanes[0].keyframes[0].frame, 0);
+
+		let reparsed = deserialize_puppet(&serialize_puppet(&puppet)).unwrap();
+		assert_eq!(reparsed.animations, puppet.animations);
+		assert!(test_puppet().animations.is_empty());
+	}
+
+	#[test]
+	fn test_automations() {
+		// Automations of Inochi2D use another schema, and are left alone
+		let mut payload = test_payload();
+		payload["automation"] = json::array![{ "name": "Sway", "type": 1, "bindings": [] }];
+		let mut puppet = deserialize_puppet(&payload).unwrap();
+		assert!(puppet.automations.is_empty());
+
+		let vendors = vec![VendorData {
+			name: AUTOMATIONS_VENDOR_NAME.to_owned(),
+			payload: json::array![
+				{
+					"name": "Breathing",
+					"type": "wave",
+					"shape": "Sine",
+					"period": 4,
+					"phase": 0.25,
+					"bindings": [{ "param": 10, "axis": 1, "range": [-1, 1] }],
+				},
+				{ "name": "Blink", "type": "blink", "interval": 3, "variance": 0.5, "duration": 0.2, "bindings": [] },
+				{ "name": "Sway", "type": "unknown", "bindings": [] },
+				{ "name": "Stuck", "type": "blink", "interval": 0, "variance": 0, "duration": 0, "bindings": [] },
+			],
+		}];
+
+		let mut diag = ParseDiagnostics::new(ParseMode::Lenient);
+		deserialize_vendor_automations(&mut puppet, &vendors, &mut diag).unwrap();
+		let paths = diag.warnings().iter().map(|w| w.path.as_str()).collect::<Vec<_>>();
+		assert_eq!(paths, vec!["com.inox2d.automations[2]", "com.inox2d.automations[3]"]);
+
+		assert_eq!(puppet.automations.len(), 2);
+		assert_eq!(
+			puppet.automations[0].generator,
+			Generator::Wave {
+				shape: WaveShape::Sine,
+				period: 4.0,
+				phase: 0.25
+			}
+		);
+		assert_eq!(
+			puppet.automations[0].bindings,
+			vec![AutomationBinding {
+				param: ParamUuid(10),
+				axis: 1,
+				range: vec2(-1.0, 1.0),
+			}]
+		);
+		assert!(serialize_puppet(&puppet)["automation"].is_null());
+
+		let written = serialize_vendor_automations(&puppet, &vendors);
+		assert_eq!(written.len(), 1);
+		let mut reparsed = test_puppet();
+		deserialize_vendor_automations(&mut reparsed, &written, &mut ParseDiagnostics::new(ParseMode::Strict)).unwrap();
+		assert_eq!(reparsed.automations, puppet.automations);
+
+		puppet.data_mut().automations.clear();
+		assert!(serialize_vendor_automations(&puppet, &vendors).is_empty());
+	}
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::json::JsonObject;
+use super::payload::{InoxParseError, InoxParseResult};
+
+type NodeDeserializer<T> = Box<dyn Fn(&JsonObject) -> InoxParseResult<T>>;
+
+/// Deserializers for custom node types, by type name.
+///
+/// Nodes of a registered type are parsed into [`InoxData::Custom`](crate::node::data::InoxData::Custom).
+/// Nodes of any other unknown type fail with [`InoxParseError::UnknownNodeType`].
+pub struct NodeRegistry<T> {
+	deserializers: HashMap<String, NodeDeserializer<T>>,
+}
+
+impl<T> NodeRegistry<T> {
+	pub fn new() -> Self {
+		Self {
+			deserializers: HashMap::new(),
+		}
+	}
+
+	/// Register the deserializer of a node type, replacing any previous one for that type.
+	pub fn register(
+		&mut self,
+		node_type: impl Into<String>,
+		deserializer: impl Fn(&JsonObject) -> InoxParseResult<T> + 'static,
+	) -> &mut Self {
+		self.deserializers.insert(node_type.into(), Box::new(deserializer));
+		self
+	}
+
+	pub fn contains(&self, node_type: &str) -> bool {
+		self.deserializers.contains_key(node_type)
+	}
+
+	/// Deserialize the data of a custom node with the deserializer registered for its type.
+	pub fn deserialize(&self, node_type: &str, obj: &JsonObject) -> InoxParseResult<T> {
+		match self.deserializers.get(node_type) {
+			Some(deserializer) => deserializer(obj),
+			None => Err(InoxParseError::UnknownNodeType(node_type.to_owned())),
+		}
+	}
+}
+
+impl<T> Default for NodeRegistry<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> fmt::Debug for NodeRegistry<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.deserializers.keys()).finish()
+	}
+}
@@ -1,3 +1,6 @@
+pub mod animation;
+pub mod automation;
+pub mod expression;
 pub mod formats;
 pub mod math;
 pub mod mesh;
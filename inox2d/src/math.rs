@@ -1,4 +1,5 @@
 pub mod camera;
+pub mod easing;
 pub mod interp;
 pub mod matrix;
 pub mod transform;
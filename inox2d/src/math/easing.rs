@@ -0,0 +1,83 @@
+/// Easing curve of a transition, mapping its progress from 0 to 1 to the amount of change from 0 to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Easing {
+	/// Constant speed
+	#[default]
+	Linear,
+	/// Starts slowly, cubic
+	EaseIn,
+	/// Ends slowly, cubic
+	EaseOut,
+	/// Starts and ends slowly, cubic
+	EaseInOut,
+}
+
+impl Easing {
+	/// Eased progress, `t` being clamped between 0 and 1.
+	pub fn apply(self, t: f32) -> f32 {
+		let t = t.clamp(0.0, 1.0);
+		match self {
+			Easing::Linear => t,
+			Easing::EaseIn => t * t * t,
+			Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+			Easing::EaseInOut => {
+				if t < 0.5 {
+					4.0 * t * t * t
+				} else {
+					1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+				}
+			}
+		}
+	}
+}
+
+/// Eased transition of a value over time.
+#[derive(Debug, Clone)]
+pub(crate) struct Fade {
+	from: f32,
+	to: f32,
+	elapsed: f32,
+	duration: f32,
+	easing: Easing,
+}
+
+impl Fade {
+	pub(crate) fn new(from: f32, to: f32, duration: f32, easing: Easing) -> Self {
+		Self {
+			from,
+			to,
+			elapsed: 0.0,
+			duration,
+			easing,
+		}
+	}
+
+	/// Advance the transition by `dt` seconds, returning the new value and whether the transition is over.
+	pub(crate) fn advance(&mut self, dt: f32) -> (f32, bool) {
+		self.elapsed += dt;
+		if self.elapsed >= self.duration {
+			return (self.to, true);
+		}
+		let t = self.elapsed / self.duration;
+		(self.from + (self.to - self.from) * self.easing.apply(t), false)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_easing_bounds() {
+		for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+			assert_eq!(easing.apply(-1.0), 0.0, "{easing:?}");
+			assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+			assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+			assert_eq!(easing.apply(2.0), 1.0, "{easing:?}");
+		}
+		assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+		assert!(Easing::EaseIn.apply(0.5) < 0.5);
+		assert!(Easing::EaseOut.apply(0.5) > 0.5);
+	}
+}
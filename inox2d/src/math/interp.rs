@@ -1,12 +1,18 @@
 use glam::Vec2;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InterpolateMode {
 	/// Round to nearest
 	Nearest,
 	/// Linear interpolation
 	Linear,
-	// there's more but I'm not adding them for now.
+	/// Hold the value of the lower point until the next one is reached
+	Stepped,
+	/// Cubic interpolation, with tangents taken from neighbouring points
+	Cubic,
+	/// Cubic bezier interpolation with flat handles, easing in and out of each point
+	Bezier,
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -60,7 +66,7 @@ fn interpolate_nearest(t: f32, range_in: InterpRange<f32>, range_out: InterpRang
 #[inline]
 fn interpolate_linear(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>) -> f32 {
 	debug_assert!(
-		range_in.beg <= t && t <= range_in.end,
+		range_in.beg.min(range_in.end) <= t && t <= range_in.beg.max(range_in.end),
 		"{} <= {} <= {}",
 		range_in.beg,
 		t,
@@ -71,13 +77,89 @@ fn interpolate_linear(t: f32, range_in: InterpRange<f32>, range_out: InterpRange
 }
 
 #[inline]
-pub fn interpolate_f32(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>, mode: InterpolateMode) -> f32 {
+fn interpolate_stepped(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>) -> f32 {
+	if t == range_in.end {
+		range_out.end
+	} else {
+		range_out.beg
+	}
+}
+
+/// Position of `t` in the range, from 0 to 1.
+#[inline]
+fn progress(t: f32, range_in: InterpRange<f32>) -> f32 {
+	(t - range_in.beg) / (range_in.end - range_in.beg)
+}
+
+/// Slope between two points, or 0 if they are at the same position.
+#[inline]
+fn slope(x0: f32, p0: f32, x1: f32, p1: f32) -> f32 {
+	if x0 == x1 {
+		0.0
+	} else {
+		(p1 - p0) / (x1 - x0)
+	}
+}
+
+/// Cubic Hermite interpolation between points 1 and 2, with finite difference tangents.
+#[inline]
+fn interpolate_cubic(t: f32, points_in: [f32; 4], points_out: [f32; 4]) -> f32 {
+	let [x0, x1, x2, x3] = points_in;
+	let [p0, p1, p2, p3] = points_out;
+
+	// At the ends of an axis, the neighbour is the point itself and the tangent becomes one-sided
+	let m1 = if x0 == x1 {
+		slope(x1, p1, x2, p2)
+	} else {
+		slope(x0, p0, x2, p2)
+	};
+	let m2 = if x2 == x3 {
+		slope(x1, p1, x2, p2)
+	} else {
+		slope(x1, p1, x3, p3)
+	};
+
+	let h = x2 - x1;
+	let s = progress(t, InterpRange::new(x1, x2));
+	let s2 = s * s;
+	let s3 = s2 * s;
+
+	(2.0 * s3 - 3.0 * s2 + 1.0) * p1 + (s3 - 2.0 * s2 + s) * h * m1 + (-2.0 * s3 + 3.0 * s2) * p2 + (s3 - s2) * h * m2
+}
+
+#[inline]
+fn interpolate_bezier(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>) -> f32 {
+	// With both handles on their own points, the curve reduces to a smoothstep
+	let s = progress(t, range_in);
+	range_out.beg + (range_out.end - range_out.beg) * s * s * (3.0 - 2.0 * s)
+}
+
+/// Interpolates between points 1 and 2, points 0 and 3 being their neighbours.
+#[inline]
+pub(crate) fn interpolate_span(t: f32, points_in: [f32; 4], points_out: [f32; 4], mode: InterpolateMode) -> f32 {
+	let range_in = InterpRange::new(points_in[1], points_in[2]);
+	let range_out = InterpRange::new(points_out[1], points_out[2]);
+
 	match mode {
 		InterpolateMode::Nearest => interpolate_nearest(t, range_in, range_out),
 		InterpolateMode::Linear => interpolate_linear(t, range_in, range_out),
+		InterpolateMode::Stepped => interpolate_stepped(t, range_in, range_out),
+		InterpolateMode::Cubic => interpolate_cubic(t, points_in, points_out),
+		InterpolateMode::Bezier => interpolate_bezier(t, range_in, range_out),
 	}
 }
 
+/// Interpolates in a range without neighbours, as if it was at both ends of an axis.
+#[inline]
+pub fn interpolate_f32(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>, mode: InterpolateMode) -> f32 {
+	interpolate_span(
+		t,
+		[range_in.beg, range_in.beg, range_in.end, range_in.end],
+		[range_out.beg, range_out.beg, range_out.end, range_out.end],
+		mode,
+	)
+}
+
 #[inline]
 pub fn interpolate_vec2(
 	t: f32,
@@ -114,73 +196,58 @@ pub fn interpolate_vec2s_additive(
 	}
 }
 
+/// Axis points around a value interpolated on two axes.
+///
+/// The value lies between points 1 and 2 of each axis. Points 0 and 3 are their outer neighbours,
+/// used for the tangents of cubic interpolation. At the ends of an axis, they are the same as the nearest point.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InterpGrid {
+	pub x: [f32; 4],
+	pub y: [f32; 4],
+}
+
+/// Values bound to the points of an [`InterpGrid`], indexed by `[iy][ix]`.
+pub type GridValues<T> = [[T; 4]; 4];
+
 #[inline]
-pub fn bi_interpolate_f32(
-	t: Vec2,
-	range_in: InterpRange<Vec2>,
-	out_top: InterpRange<f32>,
-	out_bottom: InterpRange<f32>,
-	mode: InterpolateMode,
-) -> f32 {
-	let beg = interpolate_f32(t.x, range_in.to_x(), out_top, mode);
-	let end = interpolate_f32(t.x, range_in.to_x(), out_bottom, mode);
-	interpolate_f32(t.y, range_in.to_y(), InterpRange::new(beg, end), mode)
+pub fn bi_interpolate_f32(t: Vec2, grid: InterpGrid, values: GridValues<f32>, mode: InterpolateMode) -> f32 {
+	let rows = values.map(|row| interpolate_span(t.x, grid.x, row, mode));
+	interpolate_span(t.y, grid.y, rows, mode)
 }
 
 #[inline]
-pub fn bi_interpolate_vec2(
-	t: Vec2,
-	range_in: InterpRange<Vec2>,
-	out_top: InterpRange<Vec2>,
-	out_bottom: InterpRange<Vec2>,
-	mode: InterpolateMode,
-) -> Vec2 {
-	let beg = interpolate_vec2(t.x, range_in.to_x(), out_top, mode);
-	let end = interpolate_vec2(t.x, range_in.to_x(), out_bottom, mode);
-	interpolate_vec2(t.y, range_in.to_y(), InterpRange::new(beg, end), mode)
+pub fn bi_interpolate_vec2(t: Vec2, grid: InterpGrid, values: GridValues<Vec2>, mode: InterpolateMode) -> Vec2 {
+	let x = bi_interpolate_f32(t, grid, values.map(|row| row.map(|v| v.x)), mode);
+	let y = bi_interpolate_f32(t, grid, values.map(|row| row.map(|v| v.y)), mode);
+	Vec2 { x, y }
+}
+
+/// Length of the shortest slice of the grid.
+fn grid_len<T>(values: &GridValues<&[T]>) -> usize {
+	values.iter().flatten().map(|slice| slice.len()).min().unwrap_or(0)
 }
 
 pub fn bi_interpolate_f32s_additive(
 	t: Vec2,
-	range_in: InterpRange<Vec2>,
-	out_top: InterpRange<&[f32]>,
-	out_bottom: InterpRange<&[f32]>,
+	grid: InterpGrid,
+	values: GridValues<&[f32]>,
 	mode: InterpolateMode,
 	out: &mut [f32],
 ) {
-	for (((&otb, &ote), (&obb, &obe)), o) in (out_top.beg.iter().zip(out_top.end))
-		.zip(out_bottom.beg.iter().zip(out_bottom.end))
-		.zip(out)
-	{
-		*o += bi_interpolate_f32(
-			t,
-			range_in,
-			InterpRange::new(otb, ote),
-			InterpRange::new(obb, obe),
-			mode,
-		)
+	for (i, o) in out.iter_mut().enumerate().take(grid_len(&values)) {
+		*o += bi_interpolate_f32(t, grid, values.map(|row| row.map(|slice| slice[i])), mode);
 	}
 }
 
 pub fn bi_interpolate_vec2s_additive(
 	t: Vec2,
-	range_in: InterpRange<Vec2>,
-	out_top: InterpRange<&[Vec2]>,
-	out_bottom: InterpRange<&[Vec2]>,
+	grid: InterpGrid,
+	values: GridValues<&[Vec2]>,
 	mode: InterpolateMode,
 	out: &mut [Vec2],
 ) {
-	for (((&otb, &ote), (&obb, &obe)), o) in (out_top.beg.iter().zip(out_top.end))
-		.zip(out_bottom.beg.iter().zip(out_bottom.end))
-		.zip(out)
-	{
-		*o += bi_interpolate_vec2(
-			t,
-			range_in,
-			InterpRange::new(otb, ote),
-			InterpRange::new(obb, obe),
-			mode,
-		)
+	for (i, o) in out.iter_mut().enumerate().take(grid_len(&values)) {
+		*o += bi_interpolate_vec2(t, grid, values.map(|row| row.map(|slice| slice[i])), mode);
 	}
 }
 
@@ -207,4 +274,85 @@ mod tests {
 			5.0
 		);
 	}
+
+	#[test]
+	fn test_stepped_interpolation() {
+		let range_in = InterpRange::new(0.0, 1.0);
+		let range_out = InterpRange::new(-5.0, 5.0);
+		assert_eq!(
+			interpolate_f32(0.0, range_in, range_out, InterpolateMode::Stepped),
+			-5.0
+		);
+		assert_eq!(
+			interpolate_f32(0.9, range_in, range_out, InterpolateMode::Stepped),
+			-5.0
+		);
+		assert_eq!(interpolate_f32(1.0, range_in, range_out, InterpolateMode::Stepped), 5.0);
+	}
+
+	#[test]
+	fn test_cubic_interpolation() {
+		// Goes through the points
+		let points_in = [0.0, 1.0, 2.0, 4.0];
+		let points_out = [0.0, 1.0, 0.0, 3.0];
+		assert_eq!(interpolate_cubic(1.0, points_in, points_out), 1.0);
+		assert_eq!(interpolate_cubic(2.0, points_in, points_out), 0.0);
+
+		// Tangent at point 1 is flat since both its neighbours have the same value
+		let slope_at_beg = (interpolate_cubic(1.001, points_in, points_out) - 1.0) / 0.001;
+		assert!(slope_at_beg.abs() < 0.01, "{slope_at_beg}");
+
+		// Reproduces straight lines, even with uneven spacing
+		let points_out = points_in.map(|x| 2.0 * x + 1.0);
+		let value = interpolate_cubic(1.25, points_in, points_out);
+		assert!((value - 3.5).abs() < 1e-6, "{value}");
+
+		// Without neighbours, a single range is a line too
+		let value = interpolate_f32(
+			0.25,
+			InterpRange::new(0.0, 1.0),
+			InterpRange::new(-5.0, 5.0),
+			InterpolateMode::Cubic,
+		);
+		assert!((value + 2.5).abs() < 1e-6, "{value}");
+	}
+
+	#[test]
+	fn test_bezier_interpolation() {
+		let range_in = InterpRange::new(0.0, 1.0);
+		let range_out = InterpRange::new(-5.0, 5.0);
+		assert_eq!(interpolate_f32(0.0, range_in, range_out, InterpolateMode::Bezier), -5.0);
+		assert_eq!(interpolate_f32(0.5, range_in, range_out, InterpolateMode::Bezier), 0.0);
+		assert_eq!(interpolate_f32(1.0, range_in, range_out, InterpolateMode::Bezier), 5.0);
+		// Eases in
+		assert!(interpolate_f32(0.1, range_in, range_out, InterpolateMode::Bezier) < -4.0);
+	}
+
+	#[test]
+	fn test_bi_interpolation() {
+		let grid = InterpGrid {
+			x: [0.0, 0.0, 0.5, 1.0],
+			y: [0.0, 0.0, 1.0, 1.0],
+		};
+		// f(x, y) = x + 10 * y
+		let values = grid.y.map(|y| grid.x.map(|x| x + 10.0 * y));
+		let t = Vec2::new(0.25, 0.5);
+
+		for mode in [InterpolateMode::Linear, InterpolateMode::Cubic] {
+			let value = bi_interpolate_f32(t, grid, values, mode);
+			assert!((value - 5.25).abs() < 1e-5, "{mode:?}: {value}");
+		}
+		assert_eq!(bi_interpolate_f32(t, grid, values, InterpolateMode::Stepped), 0.0);
+
+		let slices = values.map(|row| row.map(|v| [v, 2.0 * v]));
+		let mut out = [1.0; 2];
+		bi_interpolate_f32s_additive(
+			t,
+			grid,
+			slices.each_ref().map(|row| row.each_ref().map(|v| v.as_slice())),
+			InterpolateMode::Linear,
+			&mut out,
+		);
+		assert_eq!(out, [6.25, 11.5]);
+	}
 }
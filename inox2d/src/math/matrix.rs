@@ -1,10 +1,12 @@
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
 
 #[derive(thiserror::Error, Clone, Debug)]
 #[error("Couldn't construct matrix: not all lines have the same length ({0:?})")]
 pub struct Matrix2dFromSliceVecsError(Vec<usize>);
 
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Matrix2dFields<T>"))]
 pub struct Matrix2d<T> {
 	width: usize,
 	height: usize,
@@ -12,6 +14,39 @@ pub struct Matrix2d<T> {
 	data: Vec<T>,
 }
 
+/// Fields of a deserialized matrix, checked before they become a [`Matrix2d`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct Matrix2dFields<T> {
+	width: usize,
+	height: usize,
+	transposed: bool,
+	data: Vec<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<Matrix2dFields<T>> for Matrix2d<T> {
+	type Error = String;
+
+	fn try_from(fields: Matrix2dFields<T>) -> Result<Self, Self::Error> {
+		if fields.width.checked_mul(fields.height) != Some(fields.data.len()) {
+			return Err(format!(
+				"a {}x{} matrix cannot have {} elements",
+				fields.width,
+				fields.height,
+				fields.data.len()
+			));
+		}
+
+		Ok(Self {
+			width: fields.width,
+			height: fields.height,
+			transposed: fields.transposed,
+			data: fields.data,
+		})
+	}
+}
+
 impl<T> Matrix2d<T> {
 	pub fn width(&self) -> usize {
 		self.width
@@ -21,20 +56,28 @@ impl<T> Matrix2d<T> {
 		self.height
 	}
 
+	/// Bounds of the `(ix, iy)` indices, taking transposition into account.
+	pub fn size(&self) -> (usize, usize) {
+		if self.transposed {
+			(self.height, self.width)
+		} else {
+			(self.width, self.height)
+		}
+	}
+
 	pub fn get(&self, ix: usize, iy: usize) -> Option<&T> {
 		let (ix, iy) = if self.transposed { (iy, ix) } else { (ix, iy) };
 		self.data.get(iy * self.width + ix)
 	}
 
 	pub fn get_mut(&mut self, ix: usize, iy: usize) -> Option<&mut T> {
+		let (ix, iy) = if self.transposed { (iy, ix) } else { (ix, iy) };
 		self.data.get_mut(iy * self.width + ix)
 	}
 }
 
-impl<T> Index<(usize, usize)> for Matrix2d<T> {
-	type Output = T;
-
-	fn index(&self, (ix, iy): (usize, usize)) -> &Self::Output {
+impl<T> Matrix2d<T> {
+	fn data_index(&self, ix: usize, iy: usize) -> usize {
 		let (ix, iy) = if self.transposed { (iy, ix) } else { (ix, iy) };
 
 		if ix >= self.width || iy >= self.height {
@@ -44,7 +87,22 @@ impl<T> Index<(usize, usize)> for Matrix2d<T> {
 			);
 		}
 
-		&self.data[iy * self.width + ix]
+		iy * self.width + ix
+	}
+}
+
+impl<T> Index<(usize, usize)> for Matrix2d<T> {
+	type Output = T;
+
+	fn index(&self, (ix, iy): (usize, usize)) -> &Self::Output {
+		&self.data[self.data_index(ix, iy)]
+	}
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix2d<T> {
+	fn index_mut(&mut self, (ix, iy): (usize, usize)) -> &mut Self::Output {
+		let i = self.data_index(ix, iy);
+		&mut self.data[i]
 	}
 }
 
@@ -89,3 +147,12 @@ impl<T: Default + Clone> Matrix2d<T> {
 		}
 	}
 }
+
+impl<T: Clone> Matrix2d<T> {
+	/// Inverse of [`Matrix2d::from_slice_vecs`]: returns the lines of the matrix as they were given.
+	pub fn to_slice_vecs(&self) -> Vec<Vec<T>> {
+		(0..self.height)
+			.map(|iy| self.data[iy * self.width..(iy + 1) * self.width].to_vec())
+			.collect()
+	}
+}
@@ -1,6 +1,7 @@
 use glam::{EulerRot, Mat4, Quat, Vec2, Vec3};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TransformOffset {
 	/// X Y Z
 	pub translation: Vec3,
@@ -5,6 +5,7 @@ use glam::{vec2, vec3, IVec2, Vec2, Vec4};
 
 /// Mesh
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mesh {
 	/// Vertices in the mesh.
 	pub vertices: Vec<Vec2>,
@@ -45,7 +46,7 @@ impl Mesh {
 
 	/// Whether the mesh data is ready to be triangulated.
 	pub fn can_triangulate(&self) -> bool {
-		!self.indices.is_empty() && self.indices.len() % 3 == 0
+		!self.indices.is_empty() && self.indices.len().is_multiple_of(3)
 	}
 
 	/// Fixes the winding order of a mesh.
@@ -1,11 +1,22 @@
 use std::fmt;
 use std::sync::Arc;
 
+use crate::node::data::CustomNode;
+use crate::puppet::validation::ValidationReport;
 use crate::puppet::Puppet;
 
+/// Encoding of a texture stored in a model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModelTextureFormat {
+	Png,
+	Tga,
+	/// BC7 compressed blocks, see [`crate::texture::bc7`].
+	Bc7,
+}
+
 #[derive(Clone, Debug)]
 pub struct ModelTexture {
-	pub format: image::ImageFormat,
+	pub format: ModelTextureFormat,
 	pub data: Arc<[u8]>,
 }
 
@@ -34,3 +45,12 @@ pub struct Model<T = ()> {
 	pub textures: Vec<ModelTexture>,
 	pub vendors: Vec<VendorData>,
 }
+
+impl<T: CustomNode> Model<T> {
+	/// Check the references of the puppet, including the textures it uses.
+	pub fn validate(&self) -> ValidationReport {
+		let mut report = self.puppet.validate();
+		report.extend(self.puppet.validate_textures(self.textures.len()));
+		report
+	}
+}
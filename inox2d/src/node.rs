@@ -8,10 +8,12 @@ use crate::math::transform::TransformOffset;
 use data::InoxData;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(transparent)]
 pub struct InoxNodeUuid(pub(crate) u32);
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InoxNode<T = ()> {
 	pub uuid: InoxNodeUuid,
 	pub name: String,
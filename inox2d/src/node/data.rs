@@ -2,15 +2,14 @@ use glam::{Vec2, Vec3};
 
 use crate::mesh::Mesh;
 use crate::params::ParamUuid;
-use crate::physics::pendulum::rigid::RigidPendulum;
-use crate::physics::pendulum::spring::SpringPendulum;
-use crate::physics::runge_kutta::PhysicsState;
+use crate::render::NodeRenderCtx;
 use crate::texture::TextureId;
 
 use super::InoxNodeUuid;
 
 /// Blending mode.
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlendMode {
 	/// Normal blending mode.
 	#[default]
@@ -47,6 +46,7 @@ impl BlendMode {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MaskMode {
 	/// The part should be masked by the drawables specified.
 	Mask,
@@ -55,12 +55,14 @@ pub enum MaskMode {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mask {
 	pub source: InoxNodeUuid,
 	pub mode: MaskMode,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Drawable {
 	pub blend_mode: BlendMode,
 	pub tint: Vec3,
@@ -83,6 +85,7 @@ impl Drawable {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Part {
 	pub draw_state: Drawable,
 	pub mesh: Mesh,
@@ -92,32 +95,31 @@ pub struct Part {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Composite {
 	pub draw_state: Drawable,
 }
 
-// TODO: PhysicsModel should just be a flat enum with no physics state.
-// There's no reason to store a state if we're not simulating anything.
-// This can be fixed in the component refactor.
-// (I didn't want to create yet another separate PhysicsCtx just for this.)
-
 /// Physics model to use for simple physics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PhysicsModel {
 	/// Rigid pendulum
-	RigidPendulum(PhysicsState<RigidPendulum>),
+	RigidPendulum,
 
 	/// Springy pendulum
-	SpringPendulum(PhysicsState<SpringPendulum>),
+	SpringPendulum,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParamMapMode {
 	AngleLength,
 	XY,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PhysicsProps {
 	/// Gravity scale (1.0 = puppet gravity)
 	pub gravity: f32,
@@ -147,6 +149,7 @@ impl Default for PhysicsProps {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimplePhysics {
 	pub param: ParamUuid,
 
@@ -157,13 +160,10 @@ pub struct SimplePhysics {
 
 	/// Whether physics system listens to local transform only.
 	pub local_only: bool,
-
-	// TODO: same as above, this state shouldn't be here.
-	// It is only useful when simulating physics.
-	pub bob: Vec2,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InoxData<T> {
 	Node,
 	Part(Part),
@@ -203,3 +203,34 @@ impl<T> InoxData<T> {
 		}
 	}
 }
+
+impl<T: CustomNode> InoxData<T> {
+	/// Part drawn for the node: its own, or the one provided by custom data.
+	pub fn drawn_part(&self) -> Option<&Part> {
+		match self {
+			InoxData::Part(part) => Some(part),
+			InoxData::Custom(custom) => custom.part(),
+			_ => None,
+		}
+	}
+}
+
+/// Behavior of the data of custom nodes, [`InoxData::Custom`], when a puppet is updated and rendered.
+///
+/// Nothing is done by default, so that custom nodes behave like plain nodes.
+pub trait CustomNode {
+	/// Contribute to the offsets of the node, such as its transform, its zsort or the properties it is drawn with.
+	///
+	/// Called whenever the offsets are reset from the data of the node, before param bindings are added to them,
+	/// which is the case on the first update and then once the node is marked dirty.
+	fn apply_offsets(&self, _node_render_ctx: &mut NodeRenderCtx) {}
+
+	/// Part to draw for the node, which is then masked, composited and sorted like any other part.
+	///
+	/// Its mesh is read once, when the render context of the puppet is built.
+	fn part(&self) -> Option<&Part> {
+		None
+	}
+}
+
+impl CustomNode for () {}
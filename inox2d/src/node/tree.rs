@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+mod serde_impl;
+
 use std::collections::HashMap;
 use std::fmt::Display;
 
@@ -69,7 +72,7 @@ impl<T> InoxNodeTree<T> {
 		vec
 	}
 
-	pub fn ancestors(&self, uuid: InoxNodeUuid) -> indextree::Ancestors<InoxNode<T>> {
+	pub fn ancestors(&self, uuid: InoxNodeUuid) -> indextree::Ancestors<'_, InoxNode<T>> {
 		self.uuids[&uuid].ancestors(&self.arena)
 	}
 
@@ -151,6 +154,6 @@ impl<T> Display for InoxNodeTree<T> {
 }
 
 fn sort_uuids_by_zsort(mut uuid_zsorts: Vec<(InoxNodeUuid, f32)>) -> Vec<InoxNodeUuid> {
-	uuid_zsorts.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().reverse());
+	uuid_zsorts.sort_by(|a, b| b.1.total_cmp(&a.1));
 	uuid_zsorts.into_iter().map(|(uuid, _zsort)| uuid).collect()
 }
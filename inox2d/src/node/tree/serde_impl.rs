@@ -0,0 +1,58 @@
+//! Trees are serialized as their nodes in depth-first order, each with the index of its parent.
+//! The arena and the UUID map are rebuilt on deserialization, so that they always match.
+
+use std::collections::HashMap;
+
+use indextree::{Arena, NodeId};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::node::InoxNode;
+
+use super::InoxNodeTree;
+
+impl<T: Serialize> Serialize for InoxNodeTree<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let indices = (self.root.descendants(&self.arena).enumerate())
+			.map(|(i, id)| (id, i))
+			.collect::<HashMap<_, _>>();
+
+		let nodes = (self.root.descendants(&self.arena))
+			.map(|id| {
+				let node = &self.arena[id];
+				let parent = node.parent().map(|parent| indices[&parent]);
+				(node.get(), parent)
+			})
+			.collect::<Vec<_>>();
+
+		nodes.serialize(serializer)
+	}
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for InoxNodeTree<T> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let nodes = Vec::<(InoxNode<T>, Option<usize>)>::deserialize(deserializer)?;
+
+		let mut arena = Arena::with_capacity(nodes.len());
+		let mut uuids = HashMap::with_capacity(nodes.len());
+		let mut ids: Vec<NodeId> = Vec::with_capacity(nodes.len());
+		for (i, (node, parent)) in nodes.into_iter().enumerate() {
+			let uuid = node.uuid;
+			let id = arena.new_node(node);
+			if uuids.insert(uuid, id).is_some() {
+				return Err(D::Error::custom(format!("duplicate node {uuid:?}")));
+			}
+
+			// Only the first node is the root, and parents come before their children
+			match (i, parent) {
+				(0, None) => (),
+				(_, Some(parent)) if parent < i => ids[parent].append(id, &mut arena),
+				_ => return Err(D::Error::custom(format!("node {uuid:?} has an invalid parent"))),
+			}
+			ids.push(id);
+		}
+
+		let root = *ids.first().ok_or_else(|| D::Error::custom("node tree has no root"))?;
+		Ok(Self { root, arena, uuids })
+	}
+}
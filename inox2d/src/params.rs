@@ -1,13 +1,17 @@
-use glam::{vec2, Vec2};
+use std::borrow::Cow;
 
-use crate::math::interp::{bi_interpolate_f32, bi_interpolate_vec2s_additive, InterpRange, InterpolateMode};
+use glam::{Vec2, Vec3};
+
+use crate::math::interp::{bi_interpolate_f32, bi_interpolate_vec2s_additive, GridValues, InterpGrid, InterpolateMode};
 use crate::math::matrix::Matrix2d;
+use crate::node::data::{CustomNode, InoxData};
 use crate::node::InoxNodeUuid;
 use crate::puppet::Puppet;
-use crate::render::{NodeRenderCtxs, PartRenderCtx, RenderCtxKind};
+use crate::render::{DrawableOffset, NodeRenderCtxs, PartRenderCtx, RenderCtxKind};
 
 /// Parameter binding to a node. This allows to animate a node based on the value of the parameter that owns it.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Binding {
 	pub node: InoxNodeUuid,
 	pub is_set: Matrix2d<bool>,
@@ -16,6 +20,7 @@ pub struct Binding {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BindingValues {
 	ZSort(Matrix2d<f32>),
 	TransformTX(Matrix2d<f32>),
@@ -26,31 +31,186 @@ pub enum BindingValues {
 	TransformRY(Matrix2d<f32>),
 	TransformRZ(Matrix2d<f32>),
 	Deform(Matrix2d<Vec<Vec2>>),
+	/// Multiplies the opacity of a drawable.
+	Opacity(Matrix2d<f32>),
+	/// Multiplies the red component of the tint of a drawable.
+	TintR(Matrix2d<f32>),
+	TintG(Matrix2d<f32>),
+	TintB(Matrix2d<f32>),
+	/// Adds to the red component of the screen tint of a drawable.
+	ScreenTintR(Matrix2d<f32>),
+	ScreenTintG(Matrix2d<f32>),
+	ScreenTintB(Matrix2d<f32>),
+	/// Adds to the mask threshold of a drawable.
+	AlphaThreshold(Matrix2d<f32>),
+}
+
+impl BindingValues {
+	/// Name of the node property driven by the binding, as in puppet files (`transform.t.x`, `deform`...).
+	pub fn param_name(&self) -> &'static str {
+		match self {
+			BindingValues::ZSort(_) => "zSort",
+			BindingValues::TransformTX(_) => "transform.t.x",
+			BindingValues::TransformTY(_) => "transform.t.y",
+			BindingValues::TransformSX(_) => "transform.s.x",
+			BindingValues::TransformSY(_) => "transform.s.y",
+			BindingValues::TransformRX(_) => "transform.r.x",
+			BindingValues::TransformRY(_) => "transform.r.y",
+			BindingValues::TransformRZ(_) => "transform.r.z",
+			BindingValues::Deform(_) => "deform",
+			BindingValues::Opacity(_) => "opacity",
+			BindingValues::TintR(_) => "tint.r",
+			BindingValues::TintG(_) => "tint.g",
+			BindingValues::TintB(_) => "tint.b",
+			BindingValues::ScreenTintR(_) => "screenTint.r",
+			BindingValues::ScreenTintG(_) => "screenTint.g",
+			BindingValues::ScreenTintB(_) => "screenTint.b",
+			BindingValues::AlphaThreshold(_) => "alphaThreshold",
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AxisPoints {
 	pub x: Vec<f32>,
 	pub y: Vec<f32>,
 }
 
-fn ranges_out(
-	matrix: &Matrix2d<f32>,
-	x_mindex: usize,
-	x_maxdex: usize,
-	y_mindex: usize,
-	y_maxdex: usize,
-) -> (InterpRange<f32>, InterpRange<f32>) {
-	let out_top = InterpRange::new(matrix[(x_mindex, y_mindex)], matrix[(x_maxdex, y_mindex)]);
-	let out_btm = InterpRange::new(matrix[(x_mindex, y_maxdex)], matrix[(x_maxdex, y_maxdex)]);
-	(out_top, out_btm)
+impl Binding {
+	/// Fill the values of unset keypoints from the set ones, so that they are not taken as zeros.
+	///
+	/// Unset keypoints are interpolated between the closest set keypoints of their row, then of their column
+	/// for rows without any. Past the first or last set keypoint of a line, they take its value.
+	/// Bindings whose size does not match the axis points are left as they are.
+	pub fn reinterpolate(&mut self, axis_points: &AxisPoints) {
+		let is_set = &self.is_set;
+		match self.values {
+			BindingValues::ZSort(ref mut matrix)
+			| BindingValues::TransformTX(ref mut matrix)
+			| BindingValues::TransformTY(ref mut matrix)
+			| BindingValues::TransformSX(ref mut matrix)
+			| BindingValues::TransformSY(ref mut matrix)
+			| BindingValues::TransformRX(ref mut matrix)
+			| BindingValues::TransformRY(ref mut matrix)
+			| BindingValues::TransformRZ(ref mut matrix)
+			| BindingValues::Opacity(ref mut matrix)
+			| BindingValues::TintR(ref mut matrix)
+			| BindingValues::TintG(ref mut matrix)
+			| BindingValues::TintB(ref mut matrix)
+			| BindingValues::ScreenTintR(ref mut matrix)
+			| BindingValues::ScreenTintG(ref mut matrix)
+			| BindingValues::ScreenTintB(ref mut matrix)
+			| BindingValues::AlphaThreshold(ref mut matrix) => {
+				reinterpolate(matrix, is_set, axis_points, |a, b, t| a + (b - a) * t)
+			}
+			BindingValues::Deform(ref mut matrix) => reinterpolate(matrix, is_set, axis_points, |a, b, t| {
+				a.iter().zip(b).map(|(a, b)| a.lerp(*b, t)).collect()
+			}),
+		}
+	}
+}
+
+fn reinterpolate<T: Clone>(
+	values: &mut Matrix2d<T>,
+	is_set: &Matrix2d<bool>,
+	axis_points: &AxisPoints,
+	lerp: impl Fn(&T, &T, f32) -> T,
+) {
+	let size = (axis_points.x.len(), axis_points.y.len());
+	if values.size() != size || is_set.size() != size {
+		return;
+	}
+
+	let mut set = is_set.clone();
+	for iy in 0..size.1 {
+		fill_line(values, &mut set, &axis_points.x, |i| (i, iy), &lerp);
+	}
+	for ix in 0..size.0 {
+		fill_line(values, &mut set, &axis_points.y, |i| (ix, i), &lerp);
+	}
+}
+
+/// Fill the unset values of a line of keypoints, `cell` giving the matrix index of each point of the line.
+fn fill_line<T: Clone>(
+	values: &mut Matrix2d<T>,
+	set: &mut Matrix2d<bool>,
+	points: &[f32],
+	cell: impl Fn(usize) -> (usize, usize),
+	lerp: &impl Fn(&T, &T, f32) -> T,
+) {
+	let set_points = (0..points.len()).filter(|&i| set[cell(i)]).collect::<Vec<_>>();
+	let (Some(&first), Some(&last)) = (set_points.first(), set_points.last()) else {
+		return;
+	};
+
+	for i in 0..points.len() {
+		if set[cell(i)] {
+			continue;
+		}
+
+		let value = if i < first {
+			values[cell(first)].clone()
+		} else if i > last {
+			values[cell(last)].clone()
+		} else {
+			let next = set_points.partition_point(|&p| p < i);
+			let (beg, end) = (set_points[next - 1], set_points[next]);
+			let span = points[end] - points[beg];
+			if span == 0.0 {
+				// Duplicated axis points leave nothing to interpolate over
+				values[cell(beg)].clone()
+			} else {
+				lerp(&values[cell(beg)], &values[cell(end)], (points[i] - points[beg]) / span)
+			}
+		};
+		values[cell(i)] = value;
+		set[cell(i)] = true;
+	}
+}
+
+/// Indexes of the axis points around a value, see [`InterpGrid`].
+/// Indices of the axis points around a normalized value, clamped to the first and last pairs.
+fn axis_indices(axis_points: &[f32], val: f32) -> (usize, usize) {
+	let maxdex = match axis_points.binary_search_by(|a| a.total_cmp(&val)) {
+		Ok(ind) => ind + 1,
+		Err(ind) => ind,
+	};
+	let maxdex = maxdex.clamp(1, axis_points.len() - 1);
+	(maxdex - 1, maxdex)
+}
+
+fn grid_indices(mindex: usize, maxdex: usize, len: usize) -> [usize; 4] {
+	[mindex.saturating_sub(1), mindex, maxdex, (maxdex + 1).min(len - 1)]
+}
+
+fn grid_values<'a, T, U>(
+	matrix: &'a Matrix2d<T>,
+	x_indices: [usize; 4],
+	y_indices: [usize; 4],
+	f: impl Fn(&'a T) -> U,
+) -> GridValues<U> {
+	y_indices.map(|y| x_indices.map(|x| f(&matrix[(x, y)])))
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParamUuid(pub u32);
 
+/// Group of params, as organized by the rigger in Inochi Creator.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParamGroup {
+	pub uuid: u32,
+	pub name: String,
+	pub color: Vec3,
+	/// Params of the group, in display order.
+	pub params: Vec<ParamUuid>,
+}
+
 /// Parameter. A simple bounded value that is used to animate nodes through bindings.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Param {
 	pub uuid: ParamUuid,
 	pub name: String,
@@ -63,96 +223,164 @@ pub struct Param {
 }
 
 impl Param {
+	/// Nodes bound to the param, in binding order and without duplicates.
+	pub fn bound_nodes(&self) -> Vec<InoxNodeUuid> {
+		let mut nodes = Vec::new();
+		for binding in &self.bindings {
+			if !nodes.contains(&binding.node) {
+				nodes.push(binding.node);
+			}
+		}
+		nodes
+	}
+
+	/// Bindings of the param to a node.
+	pub fn bindings_of(&self, node: InoxNodeUuid) -> impl Iterator<Item = &Binding> {
+		self.bindings.iter().filter(move |binding| binding.node == node)
+	}
+
+	/// Apply the offsets of all bindings of the param at a value.
 	pub fn apply(&self, val: Vec2, node_render_ctxs: &mut NodeRenderCtxs, deform_buf: &mut [Vec2]) {
+		self.apply_filtered(val, node_render_ctxs, deform_buf, |_| true);
+	}
+
+	/// Apply the offsets of the bindings of the param to the nodes matching `filter`.
+	pub(crate) fn apply_filtered(
+		&self,
+		val: Vec2,
+		node_render_ctxs: &mut NodeRenderCtxs,
+		deform_buf: &mut [Vec2],
+		filter: impl Fn(InoxNodeUuid) -> bool,
+	) {
+		let (axis_x, axis_y) = (&self.axis_points.x, &self.axis_points.y);
+		// There is nothing to interpolate between, such params are reported by validation
+		if axis_x.len() < 2 || axis_y.len() < 2 {
+			return;
+		}
+
 		let val = val.clamp(self.min, self.max);
 		let val_normed = (val - self.min) / (self.max - self.min);
+		// Axis points may not cover the whole range of the param, values beyond them keep the values at the edges
+		let val_normed = (val_normed)
+			.max(Vec2::new(axis_x[0], axis_y[0]))
+			.min(Vec2::new(axis_x[axis_x.len() - 1], axis_y[axis_y.len() - 1]));
 
 		// calculate axis point indexes
-		let (x_mindex, x_maxdex) = {
-			let x_temp = self.axis_points.x.binary_search_by(|a| a.total_cmp(&val_normed.x));
-
-			match x_temp {
-				Ok(ind) if ind == self.axis_points.x.len() - 1 => (ind - 1, ind),
-				Ok(ind) => (ind, ind + 1),
-				Err(ind) => (ind - 1, ind),
-			}
-		};
-
-		let (y_mindex, y_maxdex) = {
-			let y_temp = self.axis_points.y.binary_search_by(|a| a.total_cmp(&val_normed.y));
-
-			match y_temp {
-				Ok(ind) if ind == self.axis_points.y.len() - 1 => (ind - 1, ind),
-				Ok(ind) => (ind, ind + 1),
-				Err(ind) => (ind - 1, ind),
-			}
+		let (x_mindex, x_maxdex) = axis_indices(axis_x, val_normed.x);
+		let (y_mindex, y_maxdex) = axis_indices(axis_y, val_normed.y);
+
+		let x_indices = grid_indices(x_mindex, x_maxdex, self.axis_points.x.len());
+		let y_indices = grid_indices(y_mindex, y_maxdex, self.axis_points.y.len());
+		let grid = InterpGrid {
+			x: x_indices.map(|i| self.axis_points.x[i]),
+			y: y_indices.map(|i| self.axis_points.y[i]),
 		};
 
 		// Apply offset on each binding
-		for binding in &self.bindings {
-			let node_offsets = node_render_ctxs.get_mut(&binding.node).unwrap();
-
-			let range_in = InterpRange::new(
-				vec2(self.axis_points.x[x_mindex], self.axis_points.y[y_mindex]),
-				vec2(self.axis_points.x[x_maxdex], self.axis_points.y[y_maxdex]),
-			);
+		for binding in self.bindings.iter().filter(|binding| filter(binding.node)) {
+			// Bindings to missing nodes are reported by validation
+			let Some(node_offsets) = node_render_ctxs.get_mut(&binding.node) else {
+				continue;
+			};
 
 			match binding.values {
-				BindingValues::ZSort(_) => {
-					// Seems complicated to do currently...
-					// Do nothing for now
+				BindingValues::ZSort(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.zsort_offset += bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::TransformTX(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
 
 					node_offsets.trans_offset.translation.x +=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::TransformTY(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
 
 					node_offsets.trans_offset.translation.y +=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::TransformSX(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
 
 					node_offsets.trans_offset.scale.x *=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::TransformSY(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
 
 					node_offsets.trans_offset.scale.y *=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::TransformRX(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
 
 					node_offsets.trans_offset.rotation.x +=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::TransformRY(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
 
 					node_offsets.trans_offset.rotation.y +=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::TransformRZ(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
 
 					node_offsets.trans_offset.rotation.z +=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::Opacity(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.opacity *=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::TintR(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.tint.x *=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::TintG(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.tint.y *=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::TintB(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.tint.z *=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::ScreenTintR(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.screen_tint.x +=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::ScreenTintG(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.screen_tint.y +=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::ScreenTintB(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.screen_tint.z +=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
+				}
+				BindingValues::AlphaThreshold(ref matrix) => {
+					let values = grid_values(matrix, x_indices, y_indices, |&v| v);
+
+					node_offsets.drawable_offset.mask_threshold +=
+						bi_interpolate_f32(val_normed, grid, values, binding.interpolate_mode);
 				}
 				BindingValues::Deform(ref matrix) => {
-					let out_top = InterpRange::new(
-						matrix[(x_mindex, y_mindex)].as_slice(),
-						matrix[(x_maxdex, y_mindex)].as_slice(),
-					);
-					let out_bottom = InterpRange::new(
-						matrix[(x_mindex, y_maxdex)].as_slice(),
-						matrix[(x_maxdex, y_maxdex)].as_slice(),
-					);
+					let values = grid_values(matrix, x_indices, y_indices, Vec::as_slice);
 
 					if let RenderCtxKind::Part(PartRenderCtx {
 						vert_offset, vert_len, ..
@@ -163,9 +391,8 @@ impl Param {
 
 						bi_interpolate_vec2s_additive(
 							val_normed,
-							range_in,
-							out_top,
-							out_bottom,
+							grid,
+							values,
 							binding.interpolate_mode,
 							&mut deform_buf[def_beg..def_end],
 						);
@@ -176,38 +403,168 @@ impl Param {
 	}
 }
 
-impl Puppet {
-	pub fn get_param(&self, uuid: ParamUuid) -> Option<&Param> {
-		self.params.get(&uuid)
+impl<T> Puppet<T> {
+	/// All params of the puppet, ordered by uuid.
+	pub fn params(&self) -> impl Iterator<Item = &Param> {
+		let mut params = self.params.values().collect::<Vec<_>>();
+		params.sort_by_key(|param| param.uuid);
+		params.into_iter()
 	}
 
-	pub fn get_param_mut(&mut self, uuid: ParamUuid) -> Option<&mut Param> {
-		self.params.get_mut(&uuid)
+	/// Params binding a node, ordered by uuid, with their bindings to it.
+	pub fn params_binding_node(&self, node: InoxNodeUuid) -> Vec<(&Param, Vec<&Binding>)> {
+		self.params()
+			.map(|param| (param, param.bindings_of(node).collect::<Vec<_>>()))
+			.filter(|(_, bindings)| !bindings.is_empty())
+			.collect()
+	}
+
+	pub fn get_param(&self, uuid: ParamUuid) -> Option<&Param> {
+		self.params.get(&uuid)
 	}
 
 	pub fn get_named_param(&self, name: &str) -> Option<&Param> {
 		self.params.get(self.param_names.get(name)?)
 	}
 
-	pub fn get_named_param_mut(&mut self, name: &str) -> Option<&mut Param> {
-		self.params.get_mut(self.param_names.get(name)?)
+	/// Current value of a param, which it keeps until sources push other values.
+	pub fn param_value(&self, uuid: ParamUuid) -> Option<Vec2> {
+		self.param_values.get(&uuid).copied()
 	}
 
-	pub fn begin_set_params(&mut self) {
-		// Reset all transform and deform offsets before applying bindings
-		for (key, value) in self.render_ctx.node_render_ctxs.iter_mut() {
-			value.trans_offset = self.nodes.get_node(*key).expect("node to be in tree").trans_offset;
+	/// Value a param would get from the values pushed to it so far in this frame, before clamping.
+	pub(crate) fn pending_param_value(&self, uuid: ParamUuid) -> Option<Vec2> {
+		match self.param_contributions.get(&uuid) {
+			Some(contributions) => Some(contributions.merge(self.params.get(&uuid)?.defaults)),
+			None => self.param_value(uuid),
 		}
+	}
+
+	/// Current value of a param by name, see [`Puppet::param_value`].
+	pub fn named_param_value(&self, name: &str) -> Option<Vec2> {
+		self.param_value(*self.param_names.get(name)?)
+	}
 
-		for v in self.render_ctx.vertex_buffers.deforms.iter_mut() {
-			*v = Vec2::ZERO;
+	/// Set every param back to its default value on the next update.
+	pub fn reset_params(&mut self) {
+		for param in self.data.params.values() {
+			if self.param_values.insert(param.uuid, param.defaults) != Some(param.defaults) {
+				self.dirty_params.insert(param.uuid);
+			}
 		}
 	}
 
+	/// Mark a node whose data was modified, so that its offsets and transforms are recomputed on the next update.
+	pub fn mark_node_dirty(&mut self, uuid: InoxNodeUuid) {
+		self.dirty_nodes.insert(uuid);
+	}
+
+	#[deprecated(note = "param values are now retained across frames, use `reset_params` to go back to the defaults")]
+	pub fn begin_set_params(&mut self) {}
+}
+
+impl<T: Clone> Puppet<T> {
+	/// Get a param to modify it. Its bindings are reapplied on the next update.
+	///
+	/// **This modifies the data of the puppet, not only this instance.** If the data is shared with other instances,
+	/// all of it is copied first, see [`Puppet::data_mut`]. To drive a param of a single instance,
+	/// push values to it with [`Puppet::set_param`] instead.
+	pub fn get_param_mut(&mut self, uuid: ParamUuid) -> Option<&mut Param> {
+		self.dirty_params.insert(uuid);
+		self.data_mut().params.get_mut(&uuid)
+	}
+
+	/// Get a param by name to modify it. Its bindings are reapplied on the next update.
+	///
+	/// Like [`Puppet::get_param_mut`], this copies the data of the puppet if it is shared with other instances.
+	pub fn get_named_param_mut(&mut self, name: &str) -> Option<&mut Param> {
+		let uuid = *self.param_names.get(name)?;
+		self.get_param_mut(uuid)
+	}
+}
+
+impl<T: CustomNode> Puppet<T> {
+	/// Merge the values pushed to each param, then update the nodes bound to params whose value changed.
+	///
+	/// Automations then physics push their own values first, physics reacting to the transforms of the previous frame.
+	/// Nothing is recomputed for an idle puppet.
 	pub fn end_set_params(&mut self, dt: f32) {
-		// TODO: find better places for these two update calls and pass elapsed time in
+		// TODO: find better places for these update calls and pass elapsed time in
+		self.update_automations(dt);
 		self.update_physics(dt, self.physics);
-		self.update_trans();
+		self.merge_params();
+		self.apply_dirty_params();
+
+		if !self.dirty_nodes.is_empty() {
+			let dirty_nodes = std::mem::take(&mut self.dirty_nodes);
+			self.update_dirty_trans(&dirty_nodes);
+			self.update_zsort();
+
+			// Keep the allocation for the next frame
+			self.dirty_nodes = dirty_nodes;
+			self.dirty_nodes.clear();
+		}
+	}
+
+	/// Merge the values pushed to each param into its current value, marking it dirty if it changed.
+	fn merge_params(&mut self) {
+		for (param_uuid, param_contributions) in self.param_contributions.drain() {
+			let Some(param) = self.data.params.get(&param_uuid) else {
+				continue;
+			};
+
+			let value = param_contributions.merge(param.defaults).clamp(param.min, param.max);
+			if self.param_values.insert(param_uuid, value) != Some(value) {
+				self.dirty_params.insert(param_uuid);
+			}
+		}
+	}
+
+	/// Reset the offsets of the nodes bound to dirty params, and apply all bindings to them again.
+	fn apply_dirty_params(&mut self) {
+		for param_uuid in self.dirty_params.drain() {
+			if let Some(param) = self.data.params.get(&param_uuid) {
+				self.dirty_nodes
+					.extend(param.bindings.iter().map(|binding| binding.node));
+			}
+		}
+
+		if self.dirty_nodes.is_empty() {
+			return;
+		}
+
+		let node_render_ctxs = &mut self.render_ctx.node_render_ctxs;
+		let deforms = self.render_ctx.vertex_buffers.deforms.as_mut_slice();
+
+		// Offsets add up, so other params bound to these nodes must be applied again too
+		for uuid in &self.dirty_nodes {
+			let (Some(node), Some(node_render_ctx)) = (self.data.nodes.get_node(*uuid), node_render_ctxs.get_mut(uuid))
+			else {
+				continue;
+			};
+
+			node_render_ctx.trans_offset = node.trans_offset;
+			node_render_ctx.zsort_offset = 0.0;
+			node_render_ctx.drawable_offset = DrawableOffset::from_data(&node.data);
+			if let InoxData::Custom(ref custom) = node.data {
+				custom.apply_offsets(node_render_ctx);
+			}
+
+			if let RenderCtxKind::Part(PartRenderCtx {
+				vert_offset, vert_len, ..
+			}) = node_render_ctx.kind
+			{
+				let def_beg = vert_offset as usize;
+				deforms[def_beg..def_beg + vert_len].fill(Vec2::ZERO);
+			}
+		}
+
+		for param in self.data.params.values() {
+			let value = self.param_values[&param.uuid];
+			param.apply_filtered(value, node_render_ctxs, deforms, |node| {
+				self.dirty_nodes.contains(&node)
+			});
+		}
 	}
 }
 
@@ -220,26 +577,419 @@ pub enum SetParamError {
 	NoParameterWithUuid(ParamUuid),
 }
 
-impl Puppet {
-	pub fn set_named_param(&mut self, param_name: &str, val: Vec2) -> Result<(), SetParamError> {
+/// Source of the values pushed with [`Puppet::set_param`].
+pub const DEFAULT_PARAM_SOURCE: &str = "default";
+/// Source of the values pushed by simple physics nodes.
+pub const PHYSICS_PARAM_SOURCE: &str = "physics";
+
+/// How a value pushed to a param combines with the values of other sources.
+///
+/// Contributions are merged in [`Puppet::end_set_params`]: forced and weighted values make up the base value,
+/// additive values are then added to it, and the sum is multiplied by multiplicative values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParamMergeMode {
+	/// Replaces the default value of the param. The last source to force a value wins.
+	#[default]
+	Forced,
+	/// Added to the base value, scaled by the weight.
+	Additive,
+	/// Multiplies the value, `weight` blending between no change and the full factor.
+	Multiplicative,
+	/// Blended with the base value by its weight. Weights adding up to more than 1 are normalized.
+	Weighted,
+}
+
+#[derive(Debug, Clone)]
+struct ParamContribution {
+	source: Cow<'static, str>,
+	value: Vec2,
+	mode: ParamMergeMode,
+	/// Weight of each axis, an axis with a zero weight is left to other sources.
+	weight: Vec2,
+}
+
+/// Values pushed to a param during a frame, one per source.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParamContributions(Vec<ParamContribution>);
+
+impl ParamContributions {
+	/// Add a contribution, replacing the previous one of the same source.
+	fn push(&mut self, contribution: ParamContribution) {
+		match self.0.iter_mut().find(|c| c.source == contribution.source) {
+			Some(previous) => *previous = contribution,
+			None => self.0.push(contribution),
+		}
+	}
+
+	/// Combine the contributions into a single value, `default` standing for the value of the param without any.
+	fn merge(&self, default: Vec2) -> Vec2 {
+		let mut base = default;
+		let mut weighted = Vec2::ZERO;
+		let mut total_weight = Vec2::ZERO;
+		let mut added = Vec2::ZERO;
+		let mut factor = Vec2::ONE;
+
+		for contribution in &self.0 {
+			let (value, weight) = (contribution.value, contribution.weight);
+			match contribution.mode {
+				ParamMergeMode::Forced => base = Vec2::select(weight.cmpeq(Vec2::ZERO), base, value),
+				ParamMergeMode::Additive => added += value * weight,
+				ParamMergeMode::Multiplicative => factor *= Vec2::ONE + (value - Vec2::ONE) * weight,
+				ParamMergeMode::Weighted => {
+					weighted += value * weight;
+					total_weight += weight;
+				}
+			}
+		}
+
+		let base = Vec2::select(
+			total_weight.cmpgt(Vec2::ONE),
+			weighted / total_weight,
+			weighted + base * (Vec2::ONE - total_weight),
+		);
+		(base + added) * factor
+	}
+}
+
+impl<T> Puppet<T> {
+	/// Push a value to a param, to be merged with the values of other sources at the end of the frame.
+	///
+	/// A source pushing several values to the same param in a frame only keeps the last one.
+	/// Params that no source pushes to during a frame keep their current value.
+	///
+	/// Sources are usually constants like [`DEFAULT_PARAM_SOURCE`], which are not copied for every push.
+	pub fn push_param(
+		&mut self,
+		source: impl Into<Cow<'static, str>>,
+		param_uuid: ParamUuid,
+		val: Vec2,
+		mode: ParamMergeMode,
+		weight: f32,
+	) -> Result<(), SetParamError> {
+		self.push_param_axes(source, param_uuid, val, mode, Vec2::splat(weight))
+	}
+
+	/// Push a value to a param with a weight for each axis, see [`Puppet::push_param`].
+	///
+	/// An axis with a zero weight gets no contribution from this source, even in [`ParamMergeMode::Forced`] mode.
+	pub fn push_param_axes(
+		&mut self,
+		source: impl Into<Cow<'static, str>>,
+		param_uuid: ParamUuid,
+		val: Vec2,
+		mode: ParamMergeMode,
+		weight: Vec2,
+	) -> Result<(), SetParamError> {
+		if !self.params.contains_key(&param_uuid) {
+			return Err(SetParamError::NoParameterWithUuid(param_uuid));
+		}
+
+		self.param_contributions
+			.entry(param_uuid)
+			.or_default()
+			.push(ParamContribution {
+				source: source.into(),
+				value: val,
+				mode,
+				weight,
+			});
+
+		Ok(())
+	}
+
+	/// Push a value to a param by name, see [`Puppet::push_param`].
+	pub fn push_named_param(
+		&mut self,
+		source: impl Into<Cow<'static, str>>,
+		param_name: &str,
+		val: Vec2,
+		mode: ParamMergeMode,
+		weight: f32,
+	) -> Result<(), SetParamError> {
 		let Some(param_uuid) = self.param_names.get(param_name) else {
 			return Err(SetParamError::NoParameterNamed(param_name.to_string()));
 		};
 
-		self.set_param(*param_uuid, val)
+		self.push_param(source, *param_uuid, val, mode, weight)
 	}
 
+	pub fn set_named_param(&mut self, param_name: &str, val: Vec2) -> Result<(), SetParamError> {
+		self.push_named_param(DEFAULT_PARAM_SOURCE, param_name, val, ParamMergeMode::Forced, 1.0)
+	}
+
+	/// Force the value of a param from the default source, see [`Puppet::push_param`].
 	pub fn set_param(&mut self, param_uuid: ParamUuid, val: Vec2) -> Result<(), SetParamError> {
-		let Some(param) = self.params.get_mut(&param_uuid) else {
-			return Err(SetParamError::NoParameterWithUuid(param_uuid));
+		self.push_param(DEFAULT_PARAM_SOURCE, param_uuid, val, ParamMergeMode::Forced, 1.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::vec2;
+
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+
+	fn axis_points(x: &[f32], y: &[f32]) -> AxisPoints {
+		AxisPoints {
+			x: x.to_vec(),
+			y: y.to_vec(),
+		}
+	}
+
+	/// Builds a binding from rows of optional values, indexed `[iy][ix]`.
+	fn tx_binding(rows: &[&[Option<f32>]]) -> Binding {
+		// Matrices of bindings are stored as `[ix][iy]` lists
+		let columns = (0..rows[0].len())
+			.map(|ix| rows.iter().map(|row| row[ix]).collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+		let is_set = columns
+			.iter()
+			.map(|c| c.iter().map(Option::is_some).collect())
+			.collect::<Vec<_>>();
+		let values = columns
+			.iter()
+			.map(|c| c.iter().map(|v| v.unwrap_or(0.0)).collect())
+			.collect::<Vec<_>>();
+
+		Binding {
+			node: InoxNodeUuid(0),
+			is_set: Matrix2d::from_slice_vecs(&is_set, true).unwrap(),
+			interpolate_mode: InterpolateMode::Linear,
+			values: BindingValues::TransformTX(Matrix2d::from_slice_vecs(&values, true).unwrap()),
+		}
+	}
+
+	fn tx_rows(binding: &Binding) -> Vec<Vec<f32>> {
+		let BindingValues::TransformTX(ref matrix) = binding.values else {
+			unreachable!()
 		};
+		let (width, height) = matrix.size();
+		(0..height)
+			.map(|iy| (0..width).map(|ix| matrix[(ix, iy)]).collect())
+			.collect()
+	}
 
-		param.apply(
-			val,
-			&mut self.render_ctx.node_render_ctxs,
-			self.render_ctx.vertex_buffers.deforms.as_mut_slice(),
+	#[test]
+	fn test_reinterpolate_corners() {
+		// Only the corners of a 3x3 grid are keyed with f(x, y) = x + 10y
+		let mut binding = tx_binding(&[
+			&[Some(0.0), None, Some(1.0)],
+			&[None, None, None],
+			&[Some(10.0), None, Some(11.0)],
+		]);
+		binding.reinterpolate(&axis_points(&[0.0, 0.5, 1.0], &[0.0, 0.5, 1.0]));
+
+		assert_eq!(
+			tx_rows(&binding),
+			vec![vec![0.0, 0.5, 1.0], vec![5.0, 5.5, 6.0], vec![10.0, 10.5, 11.0]]
 		);
+		// Which keypoints were set is kept as loaded
+		assert!(!binding.is_set[(1, 1)]);
+	}
 
-		Ok(())
+	#[test]
+	fn test_reinterpolate_uneven_axis_points() {
+		let mut binding = tx_binding(&[&[Some(0.0), None, None, Some(8.0)], &[None, Some(2.0), None, None]]);
+		binding.reinterpolate(&axis_points(&[0.0, 0.25, 0.5, 1.0], &[0.0, 1.0]));
+
+		assert_eq!(
+			tx_rows(&binding),
+			vec![vec![0.0, 2.0, 4.0, 8.0], vec![2.0, 2.0, 2.0, 2.0]]
+		);
+	}
+
+	#[test]
+	fn test_reinterpolate_edges() {
+		// Keys in the middle of the grid extend to its edges
+		let mut binding = tx_binding(&[
+			&[None, None, None, None],
+			&[None, Some(1.0), Some(3.0), None],
+			&[None, None, None, None],
+		]);
+		binding.reinterpolate(&axis_points(&[0.0, 0.2, 0.6, 1.0], &[0.0, 0.5, 1.0]));
+
+		let expected = vec![1.0, 1.0, 3.0, 3.0];
+		assert_eq!(tx_rows(&binding), vec![expected.clone(), expected.clone(), expected]);
+	}
+
+	#[test]
+	fn test_reinterpolate_deform() {
+		let set = vec![vec2(2.0, -2.0), vec2(0.0, 4.0)];
+		let is_set = Matrix2d::from_slice_vecs(&[vec![false], vec![false], vec![true]], true).unwrap();
+		let values = vec![vec![vec![Vec2::ZERO; 2]], vec![vec![Vec2::ZERO; 2]], vec![set.clone()]];
+
+		let mut binding = Binding {
+			node: InoxNodeUuid(0),
+			is_set,
+			interpolate_mode: InterpolateMode::Linear,
+			values: BindingValues::Deform(Matrix2d::from_slice_vecs(&values, true).unwrap()),
+		};
+		binding.reinterpolate(&axis_points(&[0.0, 0.5, 1.0], &[0.0]));
+
+		let BindingValues::Deform(ref matrix) = binding.values else {
+			unreachable!()
+		};
+		assert_eq!(matrix[(0, 0)], set);
+		assert_eq!(matrix[(1, 0)], set);
+	}
+
+	#[test]
+	fn test_reinterpolate_duplicate_axis_points() {
+		let mut binding = tx_binding(&[&[Some(1.0), None, Some(3.0)]]);
+		binding.reinterpolate(&axis_points(&[0.5, 0.5, 0.5], &[0.0]));
+		assert_eq!(tx_rows(&binding), vec![vec![1.0, 1.0, 3.0]]);
+	}
+
+	#[test]
+	fn test_reinterpolate_size_mismatch() {
+		let mut binding = tx_binding(&[&[Some(1.0), None]]);
+		binding.reinterpolate(&axis_points(&[0.0, 0.5, 1.0], &[0.0]));
+		assert_eq!(tx_rows(&binding), vec![vec![1.0, 0.0]]);
+	}
+
+	fn contributions(values: &[(&'static str, Vec2, ParamMergeMode, f32)]) -> ParamContributions {
+		let mut contributions = ParamContributions::default();
+		for &(source, value, mode, weight) in values {
+			contributions.push(ParamContribution {
+				source: source.into(),
+				value,
+				mode,
+				weight: Vec2::splat(weight),
+			});
+		}
+		contributions
+	}
+
+	#[test]
+	fn test_merge_modes() {
+		use ParamMergeMode::*;
+
+		let default = vec2(0.0, 0.5);
+		assert_eq!(contributions(&[]).merge(default), default);
+
+		// The last value of a source replaces its previous ones
+		let merged = contributions(&[("a", vec2(1.0, 1.0), Forced, 1.0), ("a", vec2(0.5, 0.5), Forced, 1.0)]);
+		assert_eq!(merged.merge(default), vec2(0.5, 0.5));
+
+		// Weighted values blend with the base, then are offset and scaled regardless of push order
+		let merged = contributions(&[
+			("scale", vec2(2.0, 2.0), Multiplicative, 0.5),
+			("offset", vec2(0.25, 0.0), Additive, 1.0),
+			("tracking", vec2(1.0, 1.0), Weighted, 0.5),
+			("base", vec2(-1.0, 0.0), Forced, 1.0),
+		]);
+		assert_eq!(merged.merge(default), vec2(0.25 * 1.5, 0.5 * 1.5));
+
+		// Weights over 1 are normalized, leaving nothing of the base
+		let merged = contributions(&[
+			("a", vec2(1.0, 0.0), Weighted, 1.0),
+			("b", vec2(0.0, 1.0), Weighted, 3.0),
+		]);
+		assert_eq!(merged.merge(default), vec2(0.25, 0.75));
+	}
+
+	#[test]
+	fn test_retained_params() {
+		let mut puppet = test_puppet();
+		let (body, hat) = (InoxNodeUuid(2), InoxNodeUuid(6));
+		let yaw_pitch = ParamUuid(10);
+		let defaults = puppet.get_param(yaw_pitch).unwrap().defaults;
+		assert_eq!(puppet.param_value(yaw_pitch), Some(defaults));
+		puppet.end_set_params(0.0);
+		let rest_translation = puppet.render_ctx.node_render_ctxs[&body].trans_offset.translation;
+
+		// Values are kept across frames, and clamped to the range of the param
+		puppet.set_param(yaw_pitch, vec2(2.0, 0.5)).unwrap();
+		puppet.end_set_params(0.0);
+		puppet.end_set_params(0.0);
+		assert_eq!(puppet.named_param_value("Head:: Yaw-Pitch"), Some(vec2(1.0, 0.5)));
+		let body_translation = puppet.render_ctx.node_render_ctxs[&body].trans_offset.translation;
+
+		// Nodes are only recomputed when a param bound to them changes
+		let hat_offset = puppet.render_ctx.node_render_ctxs[&hat].trans_offset;
+		let hat_render_ctx = puppet.render_ctx.node_render_ctxs.get_mut(&hat).unwrap();
+		hat_render_ctx.trans_offset.translation.x = 1000.0;
+		puppet.end_set_params(0.0);
+		assert_eq!(
+			puppet.render_ctx.node_render_ctxs[&hat].trans_offset.translation.x,
+			1000.0
+		);
+
+		puppet.set_param(yaw_pitch, vec2(-1.0, 0.5)).unwrap();
+		puppet.end_set_params(0.0);
+		assert_ne!(
+			puppet.render_ctx.node_render_ctxs[&body].trans_offset.translation,
+			body_translation
+		);
+		assert_eq!(
+			puppet.render_ctx.node_render_ctxs[&hat].trans_offset.translation.x,
+			1000.0
+		);
+
+		puppet.mark_node_dirty(hat);
+		puppet.end_set_params(0.0);
+		assert_eq!(
+			puppet.render_ctx.node_render_ctxs[&hat].trans_offset.translation,
+			hat_offset.translation
+		);
+
+		puppet.reset_params();
+		puppet.end_set_params(0.0);
+		assert_eq!(puppet.param_value(yaw_pitch), Some(defaults));
+		assert_eq!(
+			puppet.render_ctx.node_render_ctxs[&body].trans_offset.translation,
+			rest_translation
+		);
+	}
+
+	#[test]
+	fn test_apply_outside_axis_points() {
+		let puppet = test_puppet();
+		let mut param = puppet.get_param(ParamUuid(11)).unwrap().clone();
+		// Axis points that do not cover the range of the param, and a binding to a node without render context
+		param.axis_points.x = vec![0.25, 0.75];
+		let mut missing = param.bindings[0].clone();
+		missing.node = InoxNodeUuid(42);
+		param.bindings.push(missing);
+
+		let hair = InoxNodeUuid(3);
+		for (x, expected) in [(-1.0, -0.5), (-0.9, -0.5), (0.0, 0.0), (1.0, 0.5)] {
+			let mut render_ctx = puppet.render_ctx.clone();
+			param.apply(
+				vec2(x, 0.0),
+				&mut render_ctx.node_render_ctxs,
+				&mut render_ctx.vertex_buffers.deforms,
+			);
+			let rotation = render_ctx.node_render_ctxs[&hair].trans_offset.rotation.z
+				- puppet.render_ctx.node_render_ctxs[&hair].trans_offset.rotation.z;
+			assert!((rotation - expected).abs() < 1e-6, "{x}: {rotation} != {expected}");
+		}
+	}
+
+	#[test]
+	fn test_param_introspection() {
+		let puppet = test_puppet();
+		let (body, hair, hat) = (InoxNodeUuid(2), InoxNodeUuid(3), InoxNodeUuid(6));
+
+		let params = puppet.params().collect::<Vec<_>>();
+		assert_eq!(params.len(), 2);
+		assert_eq!(params[0].name, "Head:: Yaw-Pitch");
+		assert_eq!(params[0].bound_nodes(), vec![body, hair]);
+		assert_eq!(params[1].uuid, ParamUuid(11));
+
+		let hair_params = puppet.params_binding_node(hair);
+		let summary = (hair_params.iter())
+			.map(|(param, bindings)| {
+				let names = bindings.iter().map(|b| b.values.param_name()).collect::<Vec<_>>();
+				(param.uuid, names)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			summary,
+			vec![(ParamUuid(10), vec!["deform"]), (ParamUuid(11), vec!["transform.r.z"])]
+		);
+		assert!(puppet.params_binding_node(hat).is_empty());
 	}
 }
@@ -1,62 +1,204 @@
 pub mod pendulum;
 pub(crate) mod runge_kutta;
 
+use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::sync::Arc;
 
 use glam::{vec2, vec4, Vec2};
 
 use crate::node::data::{InoxData, ParamMapMode, PhysicsModel, SimplePhysics};
-use crate::puppet::{Puppet, PuppetPhysics};
+use crate::node::InoxNodeUuid;
+use crate::params::{ParamMergeMode, PHYSICS_PARAM_SOURCE};
+use crate::physics::pendulum::rigid::RigidPendulum;
+use crate::physics::pendulum::spring::SpringPendulum;
+use crate::physics::runge_kutta::PhysicsState;
+use crate::puppet::{Puppet, PuppetData, PuppetPhysics};
 use crate::render::NodeRenderCtx;
 
-impl Puppet {
+/// State of the pendulum simulated by a simple physics node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum PendulumState {
+	Rigid(PhysicsState<RigidPendulum>),
+	Spring(PhysicsState<SpringPendulum>),
+}
+
+/// State of the simulation of a simple physics node, specific to each instance of a puppet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct SimplePhysicsCtx {
+	pub bob: Vec2,
+	/// Bob before the last step, to interpolate from.
+	pub prev_bob: Vec2,
+	pub pendulum: PendulumState,
+}
+
+impl SimplePhysicsCtx {
+	pub fn new(model: PhysicsModel) -> Self {
+		Self {
+			bob: Vec2::ZERO,
+			prev_bob: Vec2::ZERO,
+			pendulum: match model {
+				PhysicsModel::RigidPendulum => PendulumState::Rigid(PhysicsState::default()),
+				PhysicsModel::SpringPendulum => PendulumState::Spring(PhysicsState::default()),
+			},
+		}
+	}
+}
+
+/// Clock stepping the physics of a puppet at a fixed rate, whatever the frame rate.
+///
+/// Elapsed time is accumulated until there is enough for a step,
+/// and the time left over is used to interpolate between the last two steps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicsClock {
+	/// Duration of a step, in seconds.
+	pub step: f32,
+	/// Maximum number of steps per update. Time beyond that is dropped, slowing the simulation down
+	/// rather than taking ever longer to catch up after a slow frame.
+	pub max_substeps: u32,
+	/// Time elapsed since the last step.
+	pub(crate) accumulator: f32,
+	paused: bool,
+}
+
+impl Default for PhysicsClock {
+	fn default() -> Self {
+		Self::new(0.01, 10)
+	}
+}
+
+impl PhysicsClock {
+	pub fn new(step: f32, max_substeps: u32) -> Self {
+		Self {
+			step,
+			max_substeps,
+			accumulator: 0.0,
+			paused: false,
+		}
+	}
+
+	/// Stop the simulation, which then keeps its current output.
+	pub fn pause(&mut self) {
+		self.paused = true;
+	}
+
+	pub fn resume(&mut self) {
+		self.paused = false;
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+
+	/// Progress between the last two steps, from 0 to 1.
+	pub fn alpha(&self) -> f32 {
+		if self.step > 0.0 {
+			(self.accumulator / self.step).clamp(0.0, 1.0)
+		} else {
+			1.0
+		}
+	}
+
+	/// Advance the clock by `dt` seconds, returning the number of steps to take.
+	fn advance(&mut self, dt: f32) -> u32 {
+		if self.paused || self.step <= 0.0 || !dt.is_finite() {
+			return 0;
+		}
+
+		self.accumulator += dt.max(0.0);
+		let steps = (self.accumulator / self.step).floor().min(self.max_substeps as f32);
+		self.accumulator -= steps * self.step;
+		if steps as u32 == self.max_substeps {
+			self.accumulator = self.accumulator.min(self.step);
+		}
+		steps as u32
+	}
+
+	fn reset(&mut self) {
+		self.accumulator = 0.0;
+	}
+}
+
+impl<T> PuppetData<T> {
+	/// Simulations of the simple physics nodes at rest.
+	pub(crate) fn rest_physics_ctxs(&self) -> HashMap<InoxNodeUuid, SimplePhysicsCtx> {
+		(self.drivers.iter())
+			.filter_map(|&uuid| match self.nodes.get_node(uuid)?.data {
+				InoxData::SimplePhysics(ref system) => Some((uuid, SimplePhysicsCtx::new(system.model_type))),
+				_ => None,
+			})
+			.collect()
+	}
+}
+
+impl<T> Puppet<T> {
 	/// Update the puppet's nodes' absolute transforms, by applying further displacements yielded by the physics system
 	/// in response to displacements caused by parameter changes
+	///
+	/// The simulation is stepped according to the [`PhysicsClock`] of the puppet.
 	pub fn update_physics(&mut self, dt: f32, puppet_physics: PuppetPhysics) {
-		for driver_uuid in self.drivers.clone() {
-			let Some(driver) = self.nodes.get_node_mut(driver_uuid) else {
+		let steps = self.physics_clock.advance(dt);
+		let (step, alpha) = (self.physics_clock.step, self.physics_clock.alpha());
+
+		let data = Arc::clone(self.data());
+		for driver_uuid in &data.drivers {
+			let Some(driver) = data.nodes.get_node(*driver_uuid) else {
 				continue;
 			};
 
-			let InoxData::SimplePhysics(ref mut system) = driver.data else {
+			let InoxData::SimplePhysics(ref system) = driver.data else {
 				continue;
 			};
 
-			let nrc = &self.render_ctx.node_render_ctxs[&driver.uuid];
+			let Some(ctx) = self.physics_ctxs.get_mut(driver_uuid) else {
+				continue;
+			};
+			let nrc = &self.render_ctx.node_render_ctxs[driver_uuid];
 
-			let output = system.update(dt, puppet_physics, nrc);
-			let param_uuid = system.param;
-			let _ = self.set_param(param_uuid, output);
+			let output = system.update(ctx, steps, step, alpha, puppet_physics, nrc);
+			let _ = self.push_param(PHYSICS_PARAM_SOURCE, system.param, output, ParamMergeMode::Forced, 1.0);
 		}
 	}
+
+	/// Bring the physics simulation back to rest, as if the puppet was just loaded.
+	pub fn reset_physics(&mut self) {
+		self.physics_ctxs = self.data.rest_physics_ctxs();
+		self.physics_clock.reset();
+	}
 }
 
 impl SimplePhysics {
-	fn update(&mut self, dt: f32, puppet_physics: PuppetPhysics, node_render_ctx: &NodeRenderCtx) -> Vec2 {
-		// Timestep is limited to 10 seconds.
-		// If you're getting 0.1 FPS, you have bigger issues to deal with.
-		let mut dt = dt.min(10.);
-
+	/// Take `steps` steps of `step` seconds, and output the bob interpolated between the last two steps.
+	fn update(
+		&self,
+		ctx: &mut SimplePhysicsCtx,
+		steps: u32,
+		step: f32,
+		alpha: f32,
+		puppet_physics: PuppetPhysics,
+		node_render_ctx: &NodeRenderCtx,
+	) -> Vec2 {
 		let anchor = self.calc_anchor(node_render_ctx);
 
-		// Minimum physics timestep: 0.01s
-		while dt > 0.01 {
-			self.tick(0.01, anchor, puppet_physics);
-			dt -= 0.01;
+		for _ in 0..steps {
+			ctx.prev_bob = ctx.bob;
+			self.tick(ctx, step, anchor, puppet_physics);
 		}
 
-		self.tick(dt, anchor, puppet_physics);
-
-		self.calc_output(anchor, node_render_ctx)
+		let bob = ctx.prev_bob.lerp(ctx.bob, alpha);
+		self.calc_output(bob, anchor, node_render_ctx)
 	}
 
-	fn tick(&mut self, dt: f32, anchor: Vec2, puppet_physics: PuppetPhysics) {
+	fn tick(&self, ctx: &mut SimplePhysicsCtx, dt: f32, anchor: Vec2, puppet_physics: PuppetPhysics) {
 		// enum dispatch, fill the branches once other systems are implemented
 		// as for inox2d, users are not expected to bring their own physics system,
 		// no need to do dynamic dispatch with something like Box<dyn SimplePhysicsSystem>
-		self.bob = match &mut self.model_type {
-			PhysicsModel::RigidPendulum(state) => state.tick(puppet_physics, &self.props, self.bob, anchor, dt),
-			PhysicsModel::SpringPendulum(state) => state.tick(puppet_physics, &self.props, self.bob, anchor, dt),
+		ctx.bob = match &mut ctx.pendulum {
+			PendulumState::Rigid(state) => state.tick(puppet_physics, &self.props, ctx.bob, anchor, dt),
+			PendulumState::Spring(state) => state.tick(puppet_physics, &self.props, ctx.bob, anchor, dt),
 		};
 	}
 
@@ -69,9 +211,8 @@ impl SimplePhysics {
 		vec2(anchor.x, anchor.y)
 	}
 
-	fn calc_output(&self, anchor: Vec2, node_render_ctx: &NodeRenderCtx) -> Vec2 {
+	fn calc_output(&self, bob: Vec2, anchor: Vec2, node_render_ctx: &NodeRenderCtx) -> Vec2 {
 		let oscale = self.props.output_scale;
-		let bob = self.bob;
 
 		// "Okay, so this is confusing. We want to translate the angle back to local space, but not the coordinates."
 		// - Asahi Lina
@@ -104,3 +245,69 @@ impl SimplePhysics {
 		param_value * oscale
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+	use crate::params::ParamUuid;
+
+	#[test]
+	fn test_clock() {
+		let mut clock = PhysicsClock::new(0.01, 4);
+		assert_eq!(clock.advance(0.025), 2);
+		assert!((clock.alpha() - 0.5).abs() < 1e-4);
+		assert_eq!(clock.advance(0.005), 1);
+		assert!(clock.alpha() < 1e-4);
+
+		// Time beyond the maximum number of steps is dropped
+		assert_eq!(clock.advance(1.0), 4);
+		assert_eq!(clock.advance(0.0), 1);
+
+		clock.pause();
+		assert_eq!(clock.advance(1.0), 0);
+		clock.resume();
+		assert_eq!(clock.advance(0.01), 1);
+	}
+
+	/// Output of the pendulum over 2 seconds at the given frame rate, sampled 30 times per second.
+	fn swing(fps: u32) -> Vec<Vec2> {
+		let mut puppet = test_puppet();
+		(0..2 * fps)
+			.filter_map(|frame| {
+				puppet.end_set_params(1.0 / fps as f32);
+				((frame + 1) % (fps / 30) == 0).then(|| puppet.param_value(ParamUuid(11)).unwrap())
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_frame_rate_independence() {
+		let (slow, fast) = (swing(30), swing(240));
+		assert_eq!(slow.len(), 60);
+		assert!(
+			slow.iter().any(|value| value.distance(slow[0]) > 0.01),
+			"the pendulum swings"
+		);
+		for (i, (slow, fast)) in slow.iter().zip(&fast).enumerate() {
+			assert!(slow.abs_diff_eq(*fast, 1e-3), "frame {i}: {slow} != {fast}");
+		}
+	}
+
+	#[test]
+	fn test_pause_and_reset() {
+		let mut puppet = test_puppet();
+		let rest = puppet.physics_ctxs.clone();
+		puppet.end_set_params(0.5);
+		assert_ne!(puppet.physics_ctxs, rest);
+
+		puppet.physics_clock.pause();
+		let paused = puppet.physics_ctxs.clone();
+		puppet.end_set_params(0.5);
+		assert_eq!(puppet.physics_ctxs, paused);
+
+		puppet.reset_physics();
+		assert_eq!(puppet.physics_ctxs, rest);
+		assert!(puppet.physics_clock.is_paused());
+	}
+}
@@ -5,7 +5,8 @@ use crate::physics::runge_kutta::{self, IsPhysicsVars, PhysicsState};
 use crate::puppet::PuppetPhysics;
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RigidPendulum {
 	pub θ: f32,
 	pub ω: f32,
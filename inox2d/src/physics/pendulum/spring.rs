@@ -5,7 +5,8 @@ use glam::{vec2, Vec2};
 use std::f32::consts::PI;
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpringPendulum {
 	pub bob_pos: Vec2,
 	pub bob_vel: Vec2,
@@ -5,7 +5,8 @@ pub trait IsPhysicsVars<const N: usize> {
 	fn set_f32s(&mut self, f32s: [f32; N]);
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PhysicsState<T> {
 	pub vars: T,
 	pub derivatives: T,
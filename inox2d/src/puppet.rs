@@ -1,16 +1,29 @@
 #![allow(dead_code)]
 
-use std::collections::HashMap;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod state;
+pub mod validation;
+
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use glam::Vec2;
 
-use crate::node::data::InoxData;
+use crate::animation::Animation;
+use crate::automation::{Automation, AutomationState};
+use crate::node::data::{CustomNode, InoxData};
 use crate::node::tree::InoxNodeTree;
 use crate::node::InoxNodeUuid;
-use crate::params::{Param, ParamUuid};
+use crate::params::{Param, ParamContributions, ParamGroup, ParamUuid};
+use crate::physics::{PhysicsClock, SimplePhysicsCtx};
 use crate::render::RenderCtx;
 
 /// Who is allowed to use the puppet?
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PuppetAllowedUsers {
 	/// Only the author(s) are allowed to use the puppet.
 	#[default]
@@ -37,6 +50,7 @@ impl fmt::Display for PuppetAllowedUsers {
 
 /// Can the puppet be redistributed?
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PuppetAllowedRedistribution {
 	/// Redistribution is prohibited
 	#[default]
@@ -67,6 +81,7 @@ impl fmt::Display for PuppetAllowedRedistribution {
 
 /// Can the puppet be modified?
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PuppetAllowedModification {
 	/// Modification is prohibited
 	#[default]
@@ -94,6 +109,7 @@ impl fmt::Display for PuppetAllowedModification {
 
 /// Terms of usage of the puppet.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PuppetUsageRights {
 	/// Who is allowed to use the puppet?
 	pub allowed_users: PuppetAllowedUsers,
@@ -141,6 +157,7 @@ impl fmt::Display for PuppetUsageRights {
 
 /// Puppet meta information.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PuppetMeta {
 	/// Name of the puppet.
 	pub name: Option<String>,
@@ -243,31 +260,44 @@ impl Default for PuppetMeta {
 
 /// Global physics parameters for the puppet.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PuppetPhysics {
 	pub pixels_per_meter: f32,
 	pub gravity: f32,
 }
 
-/// Inochi2D puppet.
+/// Data of an Inochi2D puppet, which does not change while it is animated.
+///
+/// It is shared between all instances of the puppet, see [`PuppetInstance`].
 #[derive(Clone, Debug)]
-pub struct Puppet<T = ()> {
+pub struct PuppetData<T = ()> {
 	pub meta: PuppetMeta,
 	pub physics: PuppetPhysics,
 	pub nodes: InoxNodeTree<T>,
 	pub drivers: Vec<InoxNodeUuid>,
 	pub(crate) params: HashMap<ParamUuid, Param>,
 	pub(crate) param_names: HashMap<String, ParamUuid>,
-	pub render_ctx: RenderCtx,
+	/// Groups of params. Params can also be outside of any group.
+	pub param_groups: Vec<ParamGroup>,
+	/// Keyframed animations of params, played with an [`AnimationPlayer`](crate::animation::AnimationPlayer).
+	pub animations: Vec<Animation>,
+	/// Generators driving params on every update, such as breathing or blinking.
+	pub automations: Vec<Automation>,
+	/// Render context of new instances, whose mesh buffers they share.
+	pub(crate) rest_render_ctx: RenderCtx,
 }
 
-impl<T> Puppet<T> {
+impl<T: CustomNode> PuppetData<T> {
+	/// # Panics
+	///
+	/// If the drawn meshes do not fit in vertex buffers, see [`VertexBuffers::check_len`](crate::render::VertexBuffers::check_len).
 	pub fn new(
 		meta: PuppetMeta,
 		physics: PuppetPhysics,
 		nodes: InoxNodeTree<T>,
 		named_params: HashMap<String, Param>,
 	) -> Self {
-		let render_ctx = RenderCtx::new(&nodes);
+		let rest_render_ctx = RenderCtx::new(&nodes);
 
 		let drivers = (nodes.arena.iter())
 			.filter_map(|node| {
@@ -282,7 +312,11 @@ impl<T> Puppet<T> {
 
 		let mut params = HashMap::new();
 		let mut param_names = HashMap::new();
-		for (name, param) in named_params {
+		for (name, mut param) in named_params {
+			// Unset keypoints would otherwise pull interpolated values toward zero
+			for binding in &mut param.bindings {
+				binding.reinterpolate(&param.axis_points);
+			}
 			param_names.insert(name, param.uuid);
 			params.insert(param.uuid, param);
 		}
@@ -294,7 +328,128 @@ impl<T> Puppet<T> {
 			drivers,
 			params,
 			param_names,
-			render_ctx,
+			param_groups: Vec::new(),
+			animations: Vec::new(),
+			automations: Vec::new(),
+			rest_render_ctx,
+		}
+	}
+}
+
+/// Running instance of a puppet, holding the state that changes as it is animated:
+/// param values, physics, transforms and deforms.
+///
+/// Instances of the same puppet share its [`PuppetData`], so that they are cheap to create.
+#[derive(Clone, Debug)]
+pub struct PuppetInstance<T = ()> {
+	pub(crate) data: Arc<PuppetData<T>>,
+	/// Values pushed to params since the last [`Puppet::end_set_params`].
+	pub(crate) param_contributions: HashMap<ParamUuid, ParamContributions>,
+	/// Current value of every param, kept across frames.
+	pub(crate) param_values: HashMap<ParamUuid, Vec2>,
+	/// Params whose bindings must be reapplied.
+	pub(crate) dirty_params: HashSet<ParamUuid>,
+	/// Nodes whose offsets and transforms must be recomputed.
+	pub(crate) dirty_nodes: HashSet<InoxNodeUuid>,
+	/// State of the simulation of each simple physics node.
+	pub(crate) physics_ctxs: HashMap<InoxNodeUuid, SimplePhysicsCtx>,
+	/// Pace of the physics simulation.
+	pub physics_clock: PhysicsClock,
+	/// State of each automation of the puppet, in the same order.
+	pub(crate) automation_states: Vec<AutomationState>,
+	pub render_ctx: RenderCtx,
+}
+
+/// Inochi2D puppet, as an instance of its data.
+pub type Puppet<T = ()> = PuppetInstance<T>;
+
+impl<T> PuppetInstance<T> {
+	/// New instance of a puppet, in its rest state.
+	pub fn new(data: Arc<PuppetData<T>>) -> Self {
+		let param_values = (data.params.values())
+			.map(|param| (param.uuid, param.defaults))
+			.collect();
+		let physics_ctxs = data.rest_physics_ctxs();
+
+		// Everything is applied on the first update
+		let dirty_params = data.params.keys().copied().collect();
+		let dirty_nodes = data.nodes.all_node_ids().into_iter().collect();
+
+		Self {
+			param_contributions: HashMap::new(),
+			param_values,
+			dirty_params,
+			dirty_nodes,
+			physics_ctxs,
+			physics_clock: PhysicsClock::default(),
+			automation_states: Vec::new(),
+			render_ctx: data.rest_render_ctx.clone(),
+			data,
+		}
+	}
+
+	/// Data of the puppet, shared with its other instances.
+	pub fn data(&self) -> &Arc<PuppetData<T>> {
+		&self.data
+	}
+
+	/// Another instance of the same puppet, in its rest state.
+	pub fn spawn(&self) -> Self {
+		Self::new(Arc::clone(&self.data))
+	}
+}
+
+impl<T: Clone> PuppetInstance<T> {
+	/// Data of the puppet to modify it.
+	///
+	/// **If the data is shared with other instances, all of it is copied first** and this instance stops sharing it.
+	/// Nodes whose data is modified must be marked dirty with [`Puppet::mark_node_dirty`].
+	/// Mesh buffers are built along with the data, so changes to meshes are not taken into account.
+	pub fn data_mut(&mut self) -> &mut PuppetData<T> {
+		Arc::make_mut(&mut self.data)
+	}
+}
+
+impl<T> Deref for PuppetInstance<T> {
+	type Target = PuppetData<T>;
+
+	fn deref(&self) -> &PuppetData<T> {
+		&self.data
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::vec2;
+
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+
+	#[test]
+	fn test_instances_share_data() {
+		let mut first = test_puppet();
+		let mut second = first.spawn();
+		assert!(Arc::ptr_eq(first.data(), second.data()));
+		let buffers = |puppet: &Puppet| puppet.render_ctx.vertex_buffers.clone();
+		assert!(Arc::ptr_eq(&buffers(&first).verts, &buffers(&second).verts));
+		assert!(Arc::ptr_eq(&buffers(&first).indices, &buffers(&second).indices));
+
+		first.set_param(ParamUuid(10), vec2(0.5, 0.5)).unwrap();
+		first.end_set_params(0.1);
+		second.end_set_params(0.1);
+		assert_eq!(first.param_value(ParamUuid(10)), Some(vec2(0.5, 0.5)));
+		assert_eq!(second.param_value(ParamUuid(10)), Some(vec2(0.0, 0.0)));
+
+		// Moving the first puppet swings only its pendulum
+		for _ in 0..10 {
+			first.end_set_params(0.1);
 		}
+		let bob = |puppet: &Puppet| puppet.physics_ctxs[&InoxNodeUuid(4)].bob;
+		assert_ne!(bob(&first), bob(&second));
+
+		// Modifying the data of an instance leaves the other ones untouched
+		first.data_mut().meta.name = Some("copy".to_owned());
+		assert!(!Arc::ptr_eq(first.data(), second.data()));
+		assert_eq!(second.meta.name.as_deref(), Some("Test Puppet"));
 	}
 }
@@ -0,0 +1,141 @@
+//! Puppets are serialized without their render context, which is rebuilt on deserialization.
+//! An instance is serialized as its data, and deserialized as a new instance in its rest state.
+
+use std::sync::Arc;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::animation::Animation;
+use crate::automation::Automation;
+use crate::node::data::CustomNode;
+use crate::node::tree::InoxNodeTree;
+use crate::params::{Param, ParamGroup};
+use crate::render::VertexBuffers;
+
+use super::{Puppet, PuppetData, PuppetMeta, PuppetPhysics};
+
+#[derive(Serialize)]
+#[serde(rename = "Puppet")]
+struct PuppetRef<'a, T> {
+	meta: &'a PuppetMeta,
+	physics: &'a PuppetPhysics,
+	nodes: &'a InoxNodeTree<T>,
+	params: Vec<&'a Param>,
+	param_groups: &'a [ParamGroup],
+	animations: &'a [Animation],
+	automations: &'a [Automation],
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Puppet")]
+struct PuppetOwned<T> {
+	meta: PuppetMeta,
+	physics: PuppetPhysics,
+	nodes: InoxNodeTree<T>,
+	params: Vec<Param>,
+	#[serde(default)]
+	param_groups: Vec<ParamGroup>,
+	#[serde(default)]
+	animations: Vec<Animation>,
+	#[serde(default)]
+	automations: Vec<Automation>,
+}
+
+impl<T: Serialize> Serialize for PuppetData<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut params = self.params.values().collect::<Vec<_>>();
+		params.sort_by_key(|param| param.uuid);
+
+		PuppetRef {
+			meta: &self.meta,
+			physics: &self.physics,
+			nodes: &self.nodes,
+			params,
+			param_groups: &self.param_groups,
+			animations: &self.animations,
+			automations: &self.automations,
+		}
+		.serialize(serializer)
+	}
+}
+
+impl<'de, T: Deserialize<'de> + CustomNode> Deserialize<'de> for PuppetData<T> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let puppet = PuppetOwned::<T>::deserialize(deserializer)?;
+		VertexBuffers::check_len(&puppet.nodes).map_err(D::Error::custom)?;
+		let named_params = puppet
+			.params
+			.into_iter()
+			.map(|param| (param.name.clone(), param))
+			.collect();
+		let mut deserialized = PuppetData::new(puppet.meta, puppet.physics, puppet.nodes, named_params);
+		deserialized.param_groups = puppet.param_groups;
+		deserialized.animations = puppet.animations;
+		deserialized.automations = puppet.automations;
+		Ok(deserialized)
+	}
+}
+
+impl<T: Serialize> Serialize for Puppet<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.data().serialize(serializer)
+	}
+}
+
+impl<'de, T: Deserialize<'de> + CustomNode> Deserialize<'de> for Puppet<T> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Ok(Puppet::new(Arc::new(PuppetData::deserialize(deserializer)?)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::Value;
+
+	use glam::Vec2;
+
+	use super::*;
+	use crate::formats::payload::serialize_puppet;
+	use crate::formats::payload::tests::test_puppet;
+	use crate::node::data::InoxData;
+	use crate::node::InoxNodeUuid;
+
+	#[test]
+	fn test_serde_roundtrip() {
+		let puppet = test_puppet();
+		let cached = serde_json::to_string(&puppet).unwrap();
+		let restored: Puppet = serde_json::from_str(&cached).unwrap();
+
+		assert_eq!(serialize_puppet(&puppet).dump(), serialize_puppet(&restored).dump());
+		assert_eq!(cached, serde_json::to_string(&restored).unwrap());
+	}
+
+	#[test]
+	fn test_serde_rejects_inconsistent_data() {
+		let cached = serde_json::to_value(test_puppet()).unwrap();
+
+		let mut bad_matrix = cached.clone();
+		bad_matrix["params"][0]["bindings"][0]["values"]["TransformTX"]["width"] = Value::from(100);
+		assert!(serde_json::from_value::<Puppet>(bad_matrix).is_err());
+
+		let mut bad_parent = cached.clone();
+		bad_parent["nodes"][1][1] = Value::from(100);
+		assert!(serde_json::from_value::<Puppet>(bad_parent).is_err());
+
+		let mut duplicate_node = cached;
+		let root = duplicate_node["nodes"][0][0].clone();
+		duplicate_node["nodes"][1][0] = root;
+		assert!(serde_json::from_value::<Puppet>(duplicate_node).is_err());
+
+		// Meshes too large for u16 indices must not reach the render context
+		let mut puppet = test_puppet();
+		let InoxData::Part(ref mut hair) = puppet.data_mut().nodes.get_node_mut(InoxNodeUuid(3)).unwrap().data else {
+			panic!("Hair is not a part");
+		};
+		hair.mesh.vertices.resize(70000, Vec2::ZERO);
+		hair.mesh.uvs.resize(70000, Vec2::ZERO);
+		let error = serde_json::from_value::<Puppet>(serde_json::to_value(puppet).unwrap()).unwrap_err();
+		assert!(error.to_string().contains("mesh vertex count"), "{error}");
+	}
+}
@@ -0,0 +1,254 @@
+//! Snapshots of the runtime state of puppet instances, to go back to them later,
+//! e.g. to roll back a networked puppet or to replay a recording deterministically.
+
+use std::mem;
+
+use glam::Vec2;
+
+use crate::automation::AutomationState;
+use crate::math::transform::TransformOffset;
+use crate::node::InoxNodeUuid;
+use crate::params::ParamUuid;
+use crate::physics::SimplePhysicsCtx;
+use crate::render::DrawableOffset;
+
+use super::Puppet;
+
+/// Offsets of a node, added to its data by param bindings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct NodeState {
+	uuid: InoxNodeUuid,
+	trans_offset: TransformOffset,
+	zsort_offset: f32,
+	drawable_offset: DrawableOffset,
+}
+
+/// Runtime state of a puppet instance, taken with [`Puppet::snapshot`].
+///
+/// Absolute transforms and draw order are left out, as they are recomputed from the offsets of the nodes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PuppetState {
+	/// Values of the params, ordered by uuid.
+	params: Vec<(ParamUuid, Vec2)>,
+	/// Simulations of the simple physics nodes, ordered by uuid.
+	physics: Vec<(InoxNodeUuid, SimplePhysicsCtx)>,
+	/// Time left over by the physics clock since its last step.
+	physics_accumulator: f32,
+	automations: Vec<AutomationState>,
+	/// Offsets of the nodes, ordered by uuid.
+	nodes: Vec<NodeState>,
+	deforms: Vec<Vec2>,
+	/// Params and nodes still to be updated, only found in puppets that were never updated.
+	dirty_params: Vec<ParamUuid>,
+	dirty_nodes: Vec<InoxNodeUuid>,
+}
+
+impl PuppetState {
+	/// Value of a param in this state.
+	pub fn param_value(&self, uuid: ParamUuid) -> Option<Vec2> {
+		let i = self.params.binary_search_by_key(&uuid, |&(uuid, _)| uuid).ok()?;
+		Some(self.params[i].1)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RestoreStateError {
+	#[error("No parameter with uuid {0:?}")]
+	UnknownParam(ParamUuid),
+
+	#[error("No node with uuid {0:?}")]
+	UnknownNode(InoxNodeUuid),
+
+	#[error("Node {0:?} does not simulate the same physics model")]
+	PhysicsModelMismatch(InoxNodeUuid),
+
+	#[error("State has {found} deformed vertices, but the puppet has {expected}")]
+	DeformsMismatch { expected: usize, found: usize },
+}
+
+fn sorted<K: Ord + Copy, V: Copy>(map: impl Iterator<Item = (K, V)>) -> Vec<(K, V)> {
+	let mut entries = map.collect::<Vec<_>>();
+	entries.sort_by_key(|&(key, _)| key);
+	entries
+}
+
+impl<T> Puppet<T> {
+	/// Snapshot of the runtime state of the puppet: param values, physics and the time left over by its clock,
+	/// automations, node offsets and deforms.
+	///
+	/// Values pushed since the last [`Puppet::end_set_params`] are not part of it.
+	pub fn snapshot(&self) -> PuppetState {
+		let nodes = sorted(self.render_ctx.node_render_ctxs.iter().map(|(&uuid, node_render_ctx)| {
+			let state = NodeState {
+				uuid,
+				trans_offset: node_render_ctx.trans_offset,
+				zsort_offset: node_render_ctx.zsort_offset,
+				drawable_offset: node_render_ctx.drawable_offset,
+			};
+			(uuid, state)
+		}));
+
+		let mut dirty_params = self.dirty_params.iter().copied().collect::<Vec<_>>();
+		dirty_params.sort();
+		let mut dirty_nodes = self.dirty_nodes.iter().copied().collect::<Vec<_>>();
+		dirty_nodes.sort();
+
+		PuppetState {
+			params: sorted(self.param_values.iter().map(|(&uuid, &value)| (uuid, value))),
+			physics: sorted(self.physics_ctxs.iter().map(|(&uuid, &ctx)| (uuid, ctx))),
+			physics_accumulator: self.physics_clock.accumulator,
+			automations: self.automation_states.clone(),
+			nodes: nodes.into_iter().map(|(_, state)| state).collect(),
+			deforms: self.render_ctx.vertex_buffers.deforms.clone(),
+			dirty_params,
+			dirty_nodes,
+		}
+	}
+
+	/// Bring the puppet back to a state taken with [`Puppet::snapshot`], from this instance or another one.
+	///
+	/// Values pushed since the last [`Puppet::end_set_params`] are dropped.
+	/// Updating the puppet then gives exactly the same results as it did after the snapshot.
+	/// Nothing is changed if the state does not fit the puppet.
+	///
+	/// The step, substeps and pause state of [`Puppet::physics_clock`] are left as they are, for the caller to manage.
+	pub fn restore(&mut self, state: &PuppetState) -> Result<(), RestoreStateError> {
+		let deforms = &self.render_ctx.vertex_buffers.deforms;
+		if state.deforms.len() != deforms.len() {
+			return Err(RestoreStateError::DeformsMismatch {
+				expected: deforms.len(),
+				found: state.deforms.len(),
+			});
+		}
+		if let Some(&(uuid, _)) = (state.params.iter()).find(|(uuid, _)| !self.params.contains_key(uuid)) {
+			return Err(RestoreStateError::UnknownParam(uuid));
+		}
+		if let Some(node) = (state.nodes.iter()).find(|node| !self.render_ctx.node_render_ctxs.contains_key(&node.uuid))
+		{
+			return Err(RestoreStateError::UnknownNode(node.uuid));
+		}
+		for (uuid, ctx) in &state.physics {
+			let current = self
+				.physics_ctxs
+				.get(uuid)
+				.ok_or(RestoreStateError::UnknownNode(*uuid))?;
+			if mem::discriminant(&current.pendulum) != mem::discriminant(&ctx.pendulum) {
+				return Err(RestoreStateError::PhysicsModelMismatch(*uuid));
+			}
+		}
+
+		self.param_contributions.clear();
+		self.param_values.extend(state.params.iter().copied());
+		self.physics_ctxs.extend(state.physics.iter().copied());
+		self.physics_clock.accumulator = state.physics_accumulator;
+		self.automation_states.clone_from(&state.automations);
+
+		for node in &state.nodes {
+			let node_render_ctx = self.render_ctx.node_render_ctxs.get_mut(&node.uuid).unwrap();
+			node_render_ctx.trans_offset = node.trans_offset;
+			node_render_ctx.zsort_offset = node.zsort_offset;
+			node_render_ctx.drawable_offset = node.drawable_offset;
+		}
+		(self.render_ctx.vertex_buffers.deforms).copy_from_slice(&state.deforms);
+
+		self.dirty_params = state.dirty_params.iter().copied().collect();
+		self.dirty_nodes = state.dirty_nodes.iter().copied().collect();
+
+		self.update_trans();
+		self.update_zsort();
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::{vec2, Mat4};
+
+	use super::*;
+	use crate::automation::{Automation, AutomationBinding, Generator};
+	use crate::formats::payload::tests::test_puppet;
+	use crate::physics::PhysicsClock;
+
+	fn test_puppet_with_automation() -> Puppet {
+		let mut puppet = test_puppet();
+		puppet.data_mut().automations.push(Automation::new(
+			"noise",
+			Generator::Noise { frequency: 3.0 },
+			vec![AutomationBinding {
+				param: ParamUuid(10),
+				axis: 1,
+				range: vec2(-1.0, 1.0),
+			}],
+		));
+		puppet
+	}
+
+	/// Moves the head around, returning the state and absolute transforms after every frame.
+	fn play(puppet: &mut Puppet, frames: usize) -> Vec<(PuppetState, Vec<(InoxNodeUuid, Mat4)>)> {
+		(0..frames)
+			.map(|i| {
+				let x = (i as f32 * 0.3).sin();
+				puppet.set_param(ParamUuid(10), vec2(x, 0.0)).unwrap();
+				puppet.end_set_params(1.0 / 60.0);
+				let transforms = (puppet.render_ctx.node_render_ctxs.iter()).map(|(&uuid, ctx)| (uuid, ctx.trans));
+				(puppet.snapshot(), sorted(transforms))
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_restore_replays_exactly() {
+		let mut puppet = test_puppet_with_automation();
+		play(&mut puppet, 30);
+		let state = puppet.snapshot();
+		let expected = play(&mut puppet, 30);
+
+		// On the same instance, after it moved on
+		puppet.restore(&state).unwrap();
+		assert_eq!(puppet.snapshot(), state);
+		assert_eq!(play(&mut puppet, 30), expected);
+
+		// On another instance, including before its first update
+		let mut other = puppet.spawn();
+		let rest = other.snapshot();
+		other.restore(&state).unwrap();
+		assert_eq!(play(&mut other, 30), expected);
+		other.restore(&rest).unwrap();
+		let mut fresh = puppet.spawn();
+		assert_eq!(play(&mut other, 10), play(&mut fresh, 10));
+	}
+
+	#[test]
+	fn test_restore_keeps_clock_config() {
+		let mut puppet = test_puppet_with_automation();
+		play(&mut puppet, 5);
+		let state = puppet.snapshot();
+
+		let mut other = puppet.spawn();
+		other.physics_clock = PhysicsClock::new(0.005, 3);
+		other.physics_clock.pause();
+		other.restore(&state).unwrap();
+		assert_eq!(other.physics_clock.step, 0.005);
+		assert_eq!(other.physics_clock.max_substeps, 3);
+		assert!(other.physics_clock.is_paused());
+		assert_eq!(other.physics_clock.accumulator, puppet.physics_clock.accumulator);
+	}
+
+	#[test]
+	fn test_restore_mismatch() {
+		let mut puppet = test_puppet_with_automation();
+		let mut state = puppet.snapshot();
+		state.params.push((ParamUuid(42), Vec2::ZERO));
+		assert!(matches!(
+			puppet.restore(&state),
+			Err(RestoreStateError::UnknownParam(ParamUuid(42)))
+		));
+
+		let mut state = puppet.snapshot();
+		state.deforms.pop();
+		assert!(puppet.restore(&state).is_err());
+		assert_eq!(state.param_value(ParamUuid(10)), Some(Vec2::ZERO));
+	}
+}
@@ -0,0 +1,348 @@
+use std::fmt;
+
+use crate::node::data::{CustomNode, InoxData};
+use crate::node::InoxNodeUuid;
+use crate::params::{BindingValues, ParamUuid};
+use crate::render::VertexBuffers;
+use crate::texture::TextureId;
+
+use super::Puppet;
+
+/// A reference or size in a puppet that does not match the rest of the puppet.
+///
+/// Any of these would otherwise make parameter application or rendering panic.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationIssue {
+	#[error("Mask of node {node:?} refers to missing node {mask_source:?}")]
+	MissingMaskSource {
+		node: InoxNodeUuid,
+		mask_source: InoxNodeUuid,
+	},
+	#[error("Binding of param {param:?} refers to missing node {node:?}")]
+	MissingBindingNode { param: ParamUuid, node: InoxNodeUuid },
+	#[error("Simple physics node {node:?} refers to missing param {param:?}")]
+	MissingPhysicsParam { node: InoxNodeUuid, param: ParamUuid },
+	#[error("Node {node:?} refers to texture {texture:?}, but there are only {texture_count} textures")]
+	MissingTexture {
+		node: InoxNodeUuid,
+		texture: TextureId,
+		texture_count: usize,
+	},
+	#[error("Thumbnail refers to texture {thumbnail_id}, but there are only {texture_count} textures")]
+	MissingThumbnail { thumbnail_id: u32, texture_count: usize },
+	#[error("Mesh of node {node:?} has index {index}, but only {vertex_count} vertices")]
+	MeshIndexOutOfBounds {
+		node: InoxNodeUuid,
+		index: u16,
+		vertex_count: usize,
+	},
+	#[error("Mesh of node {node:?} has {vertex_count} vertices, but {uv_count} UVs")]
+	MeshUvCountMismatch {
+		node: InoxNodeUuid,
+		vertex_count: usize,
+		uv_count: usize,
+	},
+	#[error("Meshes have {vertex_count} vertices and {index_count} indices in total, but at most {max} of each can be rendered")]
+	MeshBuffersTooLarge {
+		vertex_count: usize,
+		index_count: usize,
+		max: usize,
+	},
+	#[error("Param {param:?} needs at least 2 axis points on each axis")]
+	NotEnoughAxisPoints { param: ParamUuid },
+	#[error("Binding of param {param:?} to node {node:?} has a {actual:?} matrix, expected {expected:?}")]
+	BindingSizeMismatch {
+		param: ParamUuid,
+		node: InoxNodeUuid,
+		expected: (usize, usize),
+		actual: (usize, usize),
+	},
+	#[error("Deform of param {param:?} has {actual} offsets for node {node:?}, which has {expected} vertices")]
+	DeformLengthMismatch {
+		param: ParamUuid,
+		node: InoxNodeUuid,
+		expected: usize,
+		actual: usize,
+	},
+}
+
+/// Result of [`Puppet::validate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+	pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+	pub fn is_valid(&self) -> bool {
+		self.issues.is_empty()
+	}
+
+	pub fn extend(&mut self, other: ValidationReport) {
+		self.issues.extend(other.issues);
+	}
+}
+
+impl fmt::Display for ValidationReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for issue in &self.issues {
+			writeln!(f, "- {issue}")?;
+		}
+		Ok(())
+	}
+}
+
+impl<T: CustomNode> Puppet<T> {
+	/// Check that all node, param and mesh references of the puppet point at things that exist.
+	///
+	/// Textures are not checked since the puppet does not own them, see [`Puppet::validate_textures`].
+	pub fn validate(&self) -> ValidationReport {
+		let mut issues = Vec::new();
+
+		for node in self.nodes.arena.iter() {
+			let node = node.get();
+			match node.data {
+				InoxData::Part(ref part) => {
+					let vertex_count = part.mesh.vertices.len();
+					if part.mesh.uvs.len() != vertex_count {
+						issues.push(ValidationIssue::MeshUvCountMismatch {
+							node: node.uuid,
+							vertex_count,
+							uv_count: part.mesh.uvs.len(),
+						});
+					}
+					if let Some(&index) = part.mesh.indices.iter().find(|&&i| i as usize >= vertex_count) {
+						issues.push(ValidationIssue::MeshIndexOutOfBounds {
+							node: node.uuid,
+							index,
+							vertex_count,
+						});
+					}
+				}
+				InoxData::SimplePhysics(ref simple_physics) if !self.params.contains_key(&simple_physics.param) => {
+					issues.push(ValidationIssue::MissingPhysicsParam {
+						node: node.uuid,
+						param: simple_physics.param,
+					});
+				}
+				_ => (),
+			}
+
+			let drawable = match node.data {
+				InoxData::Part(ref part) => Some(&part.draw_state),
+				InoxData::Composite(ref composite) => Some(&composite.draw_state),
+				_ => None,
+			};
+			if let Some(drawable) = drawable {
+				for mask in &drawable.masks {
+					if self.nodes.get_node(mask.source).is_none() {
+						issues.push(ValidationIssue::MissingMaskSource {
+							node: node.uuid,
+							mask_source: mask.source,
+						});
+					}
+				}
+			}
+		}
+
+		// All drawn meshes share vertex buffers indexed with u16
+		let (vertex_count, index_count) = VertexBuffers::required_len(&self.nodes);
+		let max = VertexBuffers::MAX_LEN;
+		if vertex_count > max || index_count > max {
+			issues.push(ValidationIssue::MeshBuffersTooLarge {
+				vertex_count,
+				index_count,
+				max,
+			});
+		}
+
+		let mut params = self.params.values().collect::<Vec<_>>();
+		params.sort_by_key(|param| param.uuid);
+		for param in params {
+			let expected = (param.axis_points.x.len(), param.axis_points.y.len());
+			if expected.0 < 2 || expected.1 < 2 {
+				issues.push(ValidationIssue::NotEnoughAxisPoints { param: param.uuid });
+			}
+
+			for binding in &param.bindings {
+				let Some(node) = self.nodes.get_node(binding.node) else {
+					issues.push(ValidationIssue::MissingBindingNode {
+						param: param.uuid,
+						node: binding.node,
+					});
+					continue;
+				};
+
+				let actual = match binding.values {
+					BindingValues::ZSort(ref matrix)
+					| BindingValues::TransformTX(ref matrix)
+					| BindingValues::TransformTY(ref matrix)
+					| BindingValues::TransformSX(ref matrix)
+					| BindingValues::TransformSY(ref matrix)
+					| BindingValues::TransformRX(ref matrix)
+					| BindingValues::TransformRY(ref matrix)
+					| BindingValues::TransformRZ(ref matrix)
+					| BindingValues::Opacity(ref matrix)
+					| BindingValues::TintR(ref matrix)
+					| BindingValues::TintG(ref matrix)
+					| BindingValues::TintB(ref matrix)
+					| BindingValues::ScreenTintR(ref matrix)
+					| BindingValues::ScreenTintG(ref matrix)
+					| BindingValues::ScreenTintB(ref matrix)
+					| BindingValues::AlphaThreshold(ref matrix) => matrix.size(),
+					BindingValues::Deform(ref matrix) => matrix.size(),
+				};
+				for actual in [actual, binding.is_set.size()] {
+					if actual != expected {
+						issues.push(ValidationIssue::BindingSizeMismatch {
+							param: param.uuid,
+							node: binding.node,
+							expected,
+							actual,
+						});
+					}
+				}
+
+				if let (BindingValues::Deform(ref matrix), InoxData::Part(ref part)) = (&binding.values, &node.data) {
+					let expected = part.mesh.vertices.len();
+					let mismatch = (0..actual.0)
+						.flat_map(|x| (0..actual.1).map(move |y| (x, y)))
+						.map(|(x, y)| matrix[(x, y)].len())
+						.find(|&len| len != expected);
+					if let Some(actual) = mismatch {
+						issues.push(ValidationIssue::DeformLengthMismatch {
+							param: param.uuid,
+							node: binding.node,
+							expected,
+							actual,
+						});
+					}
+				}
+			}
+		}
+
+		ValidationReport { issues }
+	}
+}
+
+impl<T> Puppet<T> {
+	/// Check that the textures and thumbnail referenced by the puppet are among the `texture_count` of its model.
+	pub fn validate_textures(&self, texture_count: usize) -> ValidationReport {
+		let mut issues = Vec::new();
+
+		for node in self.nodes.arena.iter() {
+			let node = node.get();
+			if let InoxData::Part(ref part) = node.data {
+				// Emissive and bumpmap textures are optional, with 0 standing for no texture
+				let optional = [part.tex_emissive, part.tex_bumpmap]
+					.into_iter()
+					.filter(|t| t.raw() != 0);
+				for texture in std::iter::once(part.tex_albedo).chain(optional) {
+					if texture.raw() >= texture_count {
+						issues.push(ValidationIssue::MissingTexture {
+							node: node.uuid,
+							texture,
+							texture_count,
+						});
+					}
+				}
+			}
+		}
+
+		if let Some(thumbnail_id) = self.meta.thumbnail_id {
+			if thumbnail_id != u32::MAX && thumbnail_id as usize >= texture_count {
+				issues.push(ValidationIssue::MissingThumbnail {
+					thumbnail_id,
+					texture_count,
+				});
+			}
+		}
+
+		ValidationReport { issues }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::Vec2;
+
+	use super::*;
+	use crate::formats::payload::tests::test_puppet;
+	use crate::node::data::{Mask, MaskMode};
+
+	#[test]
+	fn test_valid_puppet() {
+		let puppet = test_puppet();
+		assert_eq!(puppet.validate(), ValidationReport::default());
+		assert!(puppet.validate_textures(4).is_valid());
+		assert_eq!(
+			puppet.validate_textures(3).issues,
+			vec![ValidationIssue::MissingTexture {
+				node: InoxNodeUuid(6),
+				texture: TextureId(3),
+				texture_count: 3,
+			}]
+		);
+	}
+
+	#[test]
+	fn test_broken_references() {
+		let mut puppet = test_puppet();
+
+		let InoxData::Part(ref mut hair) = puppet.data_mut().nodes.get_node_mut(InoxNodeUuid(3)).unwrap().data else {
+			panic!("Hair is not a part");
+		};
+		hair.draw_state.masks.push(Mask {
+			source: InoxNodeUuid(42),
+			mode: MaskMode::Mask,
+		});
+		hair.mesh.indices.push(3);
+		hair.mesh.vertices.pop();
+		hair.mesh.uvs.pop();
+
+		let yaw_pitch = puppet.data_mut().params.get_mut(&ParamUuid(10)).unwrap();
+		yaw_pitch.bindings[0].node = InoxNodeUuid(43);
+		puppet.data_mut().params.remove(&ParamUuid(11));
+
+		let issues = puppet.validate().issues;
+		assert!(issues.contains(&ValidationIssue::MissingMaskSource {
+			node: InoxNodeUuid(3),
+			mask_source: InoxNodeUuid(42),
+		}));
+		assert!(issues.contains(&ValidationIssue::MeshIndexOutOfBounds {
+			node: InoxNodeUuid(3),
+			index: 2,
+			vertex_count: 2,
+		}));
+		assert!(issues.contains(&ValidationIssue::MissingBindingNode {
+			param: ParamUuid(10),
+			node: InoxNodeUuid(43),
+		}));
+		assert!(issues.contains(&ValidationIssue::MissingPhysicsParam {
+			node: InoxNodeUuid(4),
+			param: ParamUuid(11),
+		}));
+		assert!(issues.contains(&ValidationIssue::DeformLengthMismatch {
+			param: ParamUuid(10),
+			node: InoxNodeUuid(3),
+			expected: 2,
+			actual: 3,
+		}));
+		assert_eq!(issues.len(), 5);
+	}
+
+	#[test]
+	fn test_too_many_vertices() {
+		let mut puppet = test_puppet();
+
+		let InoxData::Part(ref mut hair) = puppet.data_mut().nodes.get_node_mut(InoxNodeUuid(3)).unwrap().data else {
+			panic!("Hair is not a part");
+		};
+		hair.mesh.vertices.resize(u16::MAX as usize, Vec2::ZERO);
+		hair.mesh.uvs.resize(u16::MAX as usize, Vec2::ZERO);
+
+		let issues = puppet.validate().issues;
+		assert!(issues
+			.iter()
+			.any(|issue| matches!(issue, ValidationIssue::MeshBuffersTooLarge { max: 65535, .. })));
+	}
+}
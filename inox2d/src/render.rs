@@ -1,20 +1,28 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::sync::Arc;
 
-use glam::{vec2, Mat4, Vec2};
+use glam::{vec2, Mat4, Vec2, Vec3};
 
+use crate::formats::{check_limit, LimitExceeded};
 use crate::math::transform::TransformOffset;
 use crate::mesh::Mesh;
 use crate::model::Model;
-use crate::node::data::{Composite, InoxData, MaskMode, Part};
+use crate::node::data::{Composite, CustomNode, Drawable, InoxData, MaskMode, Part};
 use crate::node::tree::InoxNodeTree;
 use crate::node::InoxNodeUuid;
 use crate::puppet::Puppet;
 
+/// Mesh data of all drawn parts of a puppet.
+///
+/// Vertices, UVs and indices never change once built, and are shared by all instances of the puppet.
+/// Only the deforms belong to each instance.
 #[derive(Clone, Debug)]
 pub struct VertexBuffers {
-	pub verts: Vec<Vec2>,
-	pub uvs: Vec<Vec2>,
-	pub indices: Vec<u16>,
+	pub verts: Arc<Vec<Vec2>>,
+	pub uvs: Arc<Vec<Vec2>>,
+	pub indices: Arc<Vec<u16>>,
 	pub deforms: Vec<Vec2>,
 }
 
@@ -47,24 +55,60 @@ impl Default for VertexBuffers {
 		let deforms = vec![Vec2::ZERO; 4];
 
 		Self {
-			verts,
-			uvs,
-			indices,
+			verts: Arc::new(verts),
+			uvs: Arc::new(uvs),
+			indices: Arc::new(indices),
 			deforms,
 		}
 	}
 }
 
 impl VertexBuffers {
+	/// Maximum number of vertices and of indices in the buffers, so that all their offsets fit in `u16`.
+	pub const MAX_LEN: usize = u16::MAX as usize;
+
+	/// Number of vertices and of indices in the buffers of the drawn parts of a node tree.
+	pub fn required_len<T: CustomNode>(nodes: &InoxNodeTree<T>) -> (usize, usize) {
+		let buffers = Self::default();
+		let (mut vertex_count, mut index_count) = (buffers.verts.len(), buffers.indices.len());
+		for node in nodes.arena.iter() {
+			if let Some(part) = node.get().data.drawn_part() {
+				vertex_count = vertex_count.saturating_add(part.mesh.vertices.len());
+				index_count = index_count.saturating_add(part.mesh.indices.len());
+			}
+		}
+		(vertex_count, index_count)
+	}
+
+	/// Checks that the meshes of a node tree fit in the buffers, which [`RenderCtx::new`] requires.
+	pub fn check_len<T: CustomNode>(nodes: &InoxNodeTree<T>) -> Result<(), LimitExceeded> {
+		let (vertex_count, index_count) = Self::required_len(nodes);
+		check_limit("mesh vertex count", vertex_count, Self::MAX_LEN)?;
+		check_limit("mesh index count", index_count, Self::MAX_LEN)?;
+		Ok(())
+	}
+
 	/// Adds the mesh's vertices and UVs to the buffers and returns its index and vertex offset.
+	///
+	/// Shared buffers are copied first.
+	/// Indices beyond the vertices of the mesh, which validation reports, are clamped to its last vertex.
+	///
+	/// # Panics
+	///
+	/// If the buffers would get longer than [`VertexBuffers::MAX_LEN`].
 	pub fn push(&mut self, mesh: &Mesh) -> (u16, u16) {
+		let fits = |len: usize, added: usize| len.checked_add(added).is_some_and(|len| len <= Self::MAX_LEN);
+		assert!(
+			fits(self.verts.len(), mesh.vertices.len()) && fits(self.indices.len(), mesh.indices.len()),
+			"mesh buffers are limited to VertexBuffers::MAX_LEN"
+		);
 		let index_offset = self.indices.len() as u16;
 		let vert_offset = self.verts.len() as u16;
 
-		self.verts.extend_from_slice(&mesh.vertices);
-		self.uvs.extend_from_slice(&mesh.uvs);
-		self.indices
-			.extend(mesh.indices.iter().map(|index| index + vert_offset));
+		Arc::make_mut(&mut self.verts).extend_from_slice(&mesh.vertices);
+		Arc::make_mut(&mut self.uvs).extend_from_slice(&mesh.uvs);
+		let last = mesh.vertices.len().saturating_sub(1) as u16;
+		Arc::make_mut(&mut self.indices).extend(mesh.indices.iter().map(|&index| index.min(last) + vert_offset));
 		self.deforms
 			.resize(self.deforms.len() + mesh.vertices.len(), Vec2::ZERO);
 
@@ -87,10 +131,80 @@ pub enum RenderCtxKind {
 	Composite(Vec<InoxNodeUuid>),
 }
 
+/// Properties of a drawable to render it with, starting from the ones of its node and animated by param bindings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrawableOffset {
+	pub opacity: f32,
+	pub tint: Vec3,
+	pub screen_tint: Vec3,
+	pub mask_threshold: f32,
+}
+
+impl Default for DrawableOffset {
+	fn default() -> Self {
+		Self {
+			opacity: 1.0,
+			tint: Vec3::ONE,
+			screen_tint: Vec3::ZERO,
+			mask_threshold: 0.0,
+		}
+	}
+}
+
+impl From<&Drawable> for DrawableOffset {
+	fn from(drawable: &Drawable) -> Self {
+		Self {
+			opacity: drawable.opacity,
+			tint: drawable.tint,
+			screen_tint: drawable.screen_tint,
+			mask_threshold: drawable.mask_threshold,
+		}
+	}
+}
+
+impl DrawableOffset {
+	/// Properties of the drawable of a node, or the defaults for nodes that are not drawn.
+	pub fn from_data<T: CustomNode>(data: &InoxData<T>) -> Self {
+		match (data.drawn_part(), data) {
+			(Some(part), _) => Self::from(&part.draw_state),
+			(None, InoxData::Composite(composite)) => Self::from(&composite.draw_state),
+			_ => Self::default(),
+		}
+	}
+
+	/// Opacity, clamped to the range that can be rendered.
+	pub fn clamped_opacity(&self) -> f32 {
+		self.opacity.clamp(0.0, 1.0)
+	}
+
+	/// Tint, clamped to the range that can be rendered.
+	pub fn clamped_tint(&self) -> Vec3 {
+		self.tint.clamp(Vec3::ZERO, Vec3::ONE)
+	}
+
+	/// Screen tint, clamped to the range that can be rendered.
+	pub fn clamped_screen_tint(&self) -> Vec3 {
+		self.screen_tint.clamp(Vec3::ZERO, Vec3::ONE)
+	}
+
+	/// Mask threshold, clamped to the range that can be rendered.
+	pub fn clamped_mask_threshold(&self) -> f32 {
+		self.mask_threshold.clamp(0.0, 1.0)
+	}
+}
+
 #[derive(Clone, Debug)]
 pub struct NodeRenderCtx {
 	pub trans: Mat4,
 	pub trans_offset: TransformOffset,
+	/// Offset added to the zsort of the node by param bindings.
+	pub zsort_offset: f32,
+	/// Absolute zsort, combining the zsorts and offsets of the node and its ancestors.
+	pub zsort: f32,
+	/// Position of the node in a pre-order traversal of the tree, which breaks ties between equal zsorts.
+	pub(crate) tree_index: usize,
+	pub drawable_offset: DrawableOffset,
 	pub kind: RenderCtxKind,
 }
 
@@ -107,11 +221,18 @@ pub struct RenderCtx {
 }
 
 impl RenderCtx {
-	pub fn new<T>(nodes: &InoxNodeTree<T>) -> Self {
+	/// # Panics
+	///
+	/// If the drawn meshes do not fit in vertex buffers, see [`VertexBuffers::check_len`].
+	pub fn new<T: CustomNode>(nodes: &InoxNodeTree<T>) -> Self {
 		let mut vertex_buffers = VertexBuffers::default();
 		let mut root_drawables_zsorted: Vec<InoxNodeUuid> = Vec::new();
 		let mut node_render_ctxs = HashMap::new();
 
+		let tree_indices = (nodes.root.descendants(&nodes.arena).enumerate())
+			.map(|(i, id)| (nodes.arena[id].get().uuid, i))
+			.collect::<HashMap<_, _>>();
+
 		for uuid in nodes.all_node_ids() {
 			let node = nodes.get_node(uuid).unwrap();
 
@@ -120,8 +241,12 @@ impl RenderCtx {
 				NodeRenderCtx {
 					trans: Mat4::default(),
 					trans_offset: node.trans_offset,
-					kind: match node.data {
-						InoxData::Part(ref part) => {
+					zsort_offset: 0.0,
+					zsort: 0.0,
+					tree_index: tree_indices.get(&uuid).copied().unwrap_or(usize::MAX),
+					drawable_offset: DrawableOffset::from_data(&node.data),
+					kind: match (node.data.drawn_part(), &node.data) {
+						(Some(part), _) => {
 							let (index_offset, vert_offset) = vertex_buffers.push(&part.mesh);
 							RenderCtxKind::Part(PartRenderCtx {
 								index_offset,
@@ -131,7 +256,7 @@ impl RenderCtx {
 							})
 						}
 
-						InoxData::Composite(_) => RenderCtxKind::Composite(nodes.zsorted_children(uuid)),
+						(None, InoxData::Composite(_)) => RenderCtxKind::Composite(nodes.zsorted_children(uuid)),
 
 						_ => RenderCtxKind::Node,
 					},
@@ -142,44 +267,56 @@ impl RenderCtx {
 		for uuid in nodes.zsorted_root() {
 			let node = nodes.get_node(uuid).unwrap();
 
-			match node.data {
-				InoxData::Part(_) | InoxData::Composite(_) => {
-					root_drawables_zsorted.push(uuid);
-				}
-
-				_ => (),
+			if node.data.drawn_part().is_some() || node.data.is_composite() {
+				root_drawables_zsorted.push(uuid);
 			}
 		}
 
-		Self {
+		let mut render_ctx = Self {
 			vertex_buffers,
 			root_drawables_zsorted,
 			node_render_ctxs,
-		}
+		};
+		render_ctx.update_trans_where(nodes, |_| true);
+		render_ctx.update_zsort(nodes);
+		render_ctx
 	}
-}
 
-impl Puppet {
-	/// Update the puppet's nodes' absolute transforms, by combining transforms
-	/// from each node's ancestors in a pre-order traversal manner.
-	pub fn update_trans(&mut self) {
-		let root_node = self.nodes.arena[self.nodes.root].get();
-		let node_rctxs = &mut self.render_ctx.node_render_ctxs;
+	/// Update the absolute transforms of the nodes for which `is_dirty` is true, and of their descendants.
+	pub(crate) fn update_trans_where<T>(&mut self, nodes: &InoxNodeTree<T>, is_dirty: impl Fn(InoxNodeUuid) -> bool) {
+		let root_node = nodes.arena[nodes.root].get();
+		let node_rctxs = &mut self.node_render_ctxs;
+
+		// Nodes updated so far, whose descendants must be updated too
+		let mut updated = HashSet::new();
+		let root_dirty = is_dirty(root_node.uuid);
+		if root_dirty {
+			updated.insert(root_node.uuid);
+		}
 
 		// The root's absolute transform is its relative transform.
 		let root_trans = node_rctxs.get(&root_node.uuid).unwrap().trans_offset.to_matrix();
 
 		// Pre-order traversal, just the order to ensure that parents are accessed earlier than children
 		// Skip the root
-		for id in self.nodes.root.descendants(&self.nodes.arena).skip(1) {
-			let node_index = &self.nodes.arena[id];
+		for id in nodes.root.descendants(&nodes.arena).skip(1) {
+			let node_index = &nodes.arena[id];
 			let node = node_index.get();
 
+			let inherits_update = match node.lock_to_root {
+				true => root_dirty,
+				false => updated.contains(&nodes.arena[node_index.parent().unwrap()].get().uuid),
+			};
+			if !inherits_update && !is_dirty(node.uuid) {
+				continue;
+			}
+			updated.insert(node.uuid);
+
 			if node.lock_to_root {
 				let node_render_ctx = node_rctxs.get_mut(&node.uuid).unwrap();
 				node_render_ctx.trans = root_trans * node_render_ctx.trans_offset.to_matrix();
 			} else {
-				let parent = &self.nodes.arena[node_index.parent().unwrap()].get();
+				let parent = &nodes.arena[node_index.parent().unwrap()].get();
 				let parent_trans = node_rctxs.get(&parent.uuid).unwrap().trans;
 
 				let node_render_ctx = node_rctxs.get_mut(&node.uuid).unwrap();
@@ -187,6 +324,74 @@ impl Puppet {
 			}
 		}
 	}
+
+	/// Update the absolute zsorts of the nodes from their zsort offsets,
+	/// then re-sort the root drawables and composite children if their order changed.
+	pub fn update_zsort<T>(&mut self, nodes: &InoxNodeTree<T>) {
+		let node_rctxs = &mut self.node_render_ctxs;
+
+		// Pre-order traversal, so that parents are updated before their children
+		for id in nodes.root.descendants(&nodes.arena) {
+			let node_index = &nodes.arena[id];
+			let node = node_index.get();
+
+			let parent_zsort = node_index
+				.parent()
+				.map_or(0.0, |parent| node_rctxs[&nodes.arena[parent].get().uuid].zsort);
+
+			let node_render_ctx = node_rctxs.get_mut(&node.uuid).unwrap();
+			node_render_ctx.zsort = parent_zsort + node.zsort + node_render_ctx.zsort_offset;
+		}
+
+		sort_by_zsort(&mut self.root_drawables_zsorted, node_rctxs);
+
+		let composites = (node_rctxs.iter())
+			.filter(|(_, node_render_ctx)| matches!(node_render_ctx.kind, RenderCtxKind::Composite(_)))
+			.map(|(&uuid, _)| uuid)
+			.collect::<Vec<_>>();
+		for uuid in composites {
+			let RenderCtxKind::Composite(ref mut children) = node_rctxs.get_mut(&uuid).unwrap().kind else {
+				continue;
+			};
+			let mut children = mem::take(children);
+			sort_by_zsort(&mut children, node_rctxs);
+			node_rctxs.get_mut(&uuid).unwrap().kind = RenderCtxKind::Composite(children);
+		}
+	}
+}
+
+/// Draw order of two nodes: highest zsort first, then in tree order.
+fn cmp_draw_order(a: &NodeRenderCtx, b: &NodeRenderCtx) -> Ordering {
+	b.zsort.total_cmp(&a.zsort).then(a.tree_index.cmp(&b.tree_index))
+}
+
+/// Sort nodes in draw order, unless they already are, which is the case for most frames.
+fn sort_by_zsort(uuids: &mut [InoxNodeUuid], node_rctxs: &NodeRenderCtxs) {
+	let is_sorted = uuids
+		.windows(2)
+		.all(|w| cmp_draw_order(&node_rctxs[&w[0]], &node_rctxs[&w[1]]) != Ordering::Greater);
+
+	if !is_sorted {
+		uuids.sort_by(|a, b| cmp_draw_order(&node_rctxs[a], &node_rctxs[b]));
+	}
+}
+
+impl<T> Puppet<T> {
+	/// Update the puppet's nodes' absolute transforms, by combining transforms
+	/// from each node's ancestors in a pre-order traversal manner.
+	pub fn update_trans(&mut self) {
+		self.render_ctx.update_trans_where(&self.data.nodes, |_| true);
+	}
+
+	/// Update the absolute transforms of the given nodes and of the nodes depending on them.
+	pub(crate) fn update_dirty_trans(&mut self, dirty_nodes: &HashSet<InoxNodeUuid>) {
+		(self.render_ctx).update_trans_where(&self.data.nodes, |uuid| dirty_nodes.contains(&uuid));
+	}
+
+	/// Update the puppet's nodes' absolute zsorts and the resulting draw order.
+	pub fn update_zsort(&mut self) {
+		self.render_ctx.update_zsort(&self.data.nodes);
+	}
 }
 
 pub trait InoxRenderer
@@ -198,7 +403,7 @@ where
 	/// For any model-specific setup, e.g. creating buffers with specific sizes.
 	///
 	/// After this step, the provided model should be renderable.
-	fn prepare(&mut self, model: &Model) -> Result<(), Self::Error>;
+	fn prepare<T: CustomNode>(&mut self, model: &Model<T>) -> Result<(), Self::Error>;
 
 	/// Resize the renderer's viewport.
 	fn resize(&mut self, w: u32, h: u32);
@@ -211,7 +416,7 @@ where
 	/// The render pass.
 	///
 	/// Logical error if this puppet is not from the latest prepared model.
-	fn render(&self, puppet: &Puppet);
+	fn render<T: CustomNode>(&self, puppet: &Puppet<T>);
 	/// Finish one render pass.
 	fn on_end_scene(&self);
 	/// Actually make results visible, e.g. on a screen/texture.
@@ -247,27 +452,28 @@ where
 	/// When something needs to happen before drawing to the composite buffers.
 	fn begin_composite_content(&self);
 	/// Transfer content from composite buffers to normal buffers.
-	fn finish_composite_content(&self, as_mask: bool, composite: &Composite);
+	fn finish_composite_content(&self, as_mask: bool, node_render_ctx: &NodeRenderCtx, composite: &Composite);
 }
 
 pub trait InoxRendererCommon {
 	/// Draw one part, with its content properly masked.
-	fn draw_part(
+	fn draw_part<T: CustomNode>(
 		&self,
 		camera: &Mat4,
 		node_render_ctx: &NodeRenderCtx,
 		part: &Part,
 		part_render_ctx: &PartRenderCtx,
-		puppet: &Puppet,
+		puppet: &Puppet<T>,
 	);
 
 	/// Draw one composite.
-	fn draw_composite(
+	fn draw_composite<T: CustomNode>(
 		&self,
 		as_mask: bool,
 		camera: &Mat4,
+		node_render_ctx: &NodeRenderCtx,
 		composite: &Composite,
-		puppet: &Puppet,
+		puppet: &Puppet<T>,
 		children: &[InoxNodeUuid],
 	);
 
@@ -275,17 +481,17 @@ pub trait InoxRendererCommon {
 	/// and make draw calls correspondingly.
 	///
 	/// This effectively draws the complete puppet.
-	fn draw(&self, camera: &Mat4, puppet: &Puppet);
+	fn draw<T: CustomNode>(&self, camera: &Mat4, puppet: &Puppet<T>);
 }
 
-impl<T: InoxRenderer> InoxRendererCommon for T {
-	fn draw_part(
+impl<R: InoxRenderer> InoxRendererCommon for R {
+	fn draw_part<T: CustomNode>(
 		&self,
 		camera: &Mat4,
 		node_render_ctx: &NodeRenderCtx,
 		part: &Part,
 		part_render_ctx: &PartRenderCtx,
-		puppet: &Puppet,
+		puppet: &Puppet<T>,
 	) {
 		let masks = &part.draw_state.masks;
 		if !masks.is_empty() {
@@ -296,13 +502,20 @@ impl<T: InoxRenderer> InoxRendererCommon for T {
 				let mask_node = puppet.nodes.get_node(mask.source).unwrap();
 				let mask_node_render_ctx = &puppet.render_ctx.node_render_ctxs[&mask.source];
 
-				match (&mask_node.data, &mask_node_render_ctx.kind) {
-					(InoxData::Part(ref mask_part), RenderCtxKind::Part(ref mask_part_render_ctx)) => {
+				match (mask_node.data.drawn_part(), &mask_node.data, &mask_node_render_ctx.kind) {
+					(Some(mask_part), _, RenderCtxKind::Part(ref mask_part_render_ctx)) => {
 						self.draw_part_self(true, camera, mask_node_render_ctx, mask_part, mask_part_render_ctx);
 					}
 
-					(InoxData::Composite(ref mask_composite), RenderCtxKind::Composite(ref mask_children)) => {
-						self.draw_composite(true, camera, mask_composite, puppet, mask_children);
+					(_, InoxData::Composite(ref mask_composite), RenderCtxKind::Composite(ref mask_children)) => {
+						self.draw_composite(
+							true,
+							camera,
+							mask_node_render_ctx,
+							mask_composite,
+							puppet,
+							mask_children,
+						);
 					}
 
 					_ => {
@@ -319,12 +532,13 @@ impl<T: InoxRenderer> InoxRendererCommon for T {
 		}
 	}
 
-	fn draw_composite(
+	fn draw_composite<T: CustomNode>(
 		&self,
 		as_mask: bool,
 		camera: &Mat4,
+		comp_render_ctx: &NodeRenderCtx,
 		comp: &Composite,
-		puppet: &Puppet,
+		puppet: &Puppet<T>,
 		children: &[InoxNodeUuid],
 	) {
 		if children.is_empty() {
@@ -338,8 +552,8 @@ impl<T: InoxRenderer> InoxRendererCommon for T {
 			let node = puppet.nodes.get_node(uuid).unwrap();
 			let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
 
-			if let (InoxData::Part(ref part), RenderCtxKind::Part(ref part_render_ctx)) =
-				(&node.data, &node_render_ctx.kind)
+			if let (Some(part), RenderCtxKind::Part(ref part_render_ctx)) =
+				(node.data.drawn_part(), &node_render_ctx.kind)
 			{
 				if as_mask {
 					self.draw_part_self(true, camera, node_render_ctx, part, part_render_ctx);
@@ -351,21 +565,21 @@ impl<T: InoxRenderer> InoxRendererCommon for T {
 			}
 		}
 
-		self.finish_composite_content(as_mask, comp);
+		self.finish_composite_content(as_mask, comp_render_ctx, comp);
 	}
 
-	fn draw(&self, camera: &Mat4, puppet: &Puppet) {
+	fn draw<T: CustomNode>(&self, camera: &Mat4, puppet: &Puppet<T>) {
 		for &uuid in &puppet.render_ctx.root_drawables_zsorted {
 			let node = puppet.nodes.get_node(uuid).unwrap();
 			let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
 
-			match (&node.data, &node_render_ctx.kind) {
-				(InoxData::Part(ref part), RenderCtxKind::Part(ref part_render_ctx)) => {
+			match (node.data.drawn_part(), &node.data, &node_render_ctx.kind) {
+				(Some(part), _, RenderCtxKind::Part(ref part_render_ctx)) => {
 					self.draw_part(camera, node_render_ctx, part, part_render_ctx, puppet);
 				}
 
-				(InoxData::Composite(ref composite), RenderCtxKind::Composite(ref children)) => {
-					self.draw_composite(false, camera, composite, puppet, children);
+				(_, InoxData::Composite(ref composite), RenderCtxKind::Composite(ref children)) => {
+					self.draw_composite(false, camera, node_render_ctx, composite, puppet, children);
 				}
 
 				_ => {
@@ -376,3 +590,140 @@ impl<T: InoxRenderer> InoxRendererCommon for T {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::formats::payload::tests::{test_payload, test_puppet};
+	use crate::formats::payload::{deserialize_puppet_ext, InoxParseError};
+	use crate::math::interp::InterpolateMode;
+	use crate::math::matrix::Matrix2d;
+	use crate::params::{Binding, BindingValues, ParamUuid};
+
+	#[test]
+	fn test_zsort_binding() {
+		let mut puppet = test_puppet();
+		let (body, hair, accessories) = (InoxNodeUuid(2), InoxNodeUuid(3), InoxNodeUuid(5));
+		assert_eq!(puppet.render_ctx.root_drawables_zsorted, vec![accessories, body, hair]);
+
+		// Brings the hair to the front as the head turns right
+		let zsorts = vec![vec![0.0, 0.0], vec![0.0, 0.0], vec![0.5, 0.5]];
+		let param = puppet.get_param_mut(ParamUuid(10)).unwrap();
+		param.bindings.push(Binding {
+			node: hair,
+			is_set: Matrix2d::from_slice_vecs(&[vec![true; 2], vec![true; 2], vec![true; 2]], true).unwrap(),
+			interpolate_mode: InterpolateMode::Linear,
+			values: BindingValues::ZSort(Matrix2d::from_slice_vecs(&zsorts, true).unwrap()),
+		});
+
+		let mut turn_head = |x| {
+			puppet.set_param(ParamUuid(10), vec2(x, 0.0)).unwrap();
+			puppet.end_set_params(0.0);
+			puppet.render_ctx.root_drawables_zsorted.clone()
+		};
+		assert_eq!(turn_head(0.0), vec![accessories, body, hair]);
+		assert_eq!(turn_head(1.0), vec![hair, accessories, body]);
+		assert_eq!(turn_head(0.0), vec![accessories, body, hair]);
+	}
+
+	#[test]
+	fn test_drawable_bindings() {
+		let mut puppet = test_puppet();
+		let hair = InoxNodeUuid(3);
+
+		// Fades the hair out and tints it red as the head turns right
+		let param = puppet.get_param_mut(ParamUuid(10)).unwrap();
+		for values in [
+			BindingValues::Opacity(
+				Matrix2d::from_slice_vecs(&[vec![1.0; 2], vec![1.0; 2], vec![0.0; 2]], true).unwrap(),
+			),
+			BindingValues::ScreenTintR(
+				Matrix2d::from_slice_vecs(&[vec![0.0; 2], vec![0.0; 2], vec![0.5; 2]], true).unwrap(),
+			),
+		] {
+			param.bindings.push(Binding {
+				node: hair,
+				is_set: Matrix2d::from_slice_vecs(&[vec![true; 2], vec![true; 2], vec![true; 2]], true).unwrap(),
+				interpolate_mode: InterpolateMode::Linear,
+				values,
+			});
+		}
+
+		puppet.set_param(ParamUuid(10), vec2(0.5, 0.0)).unwrap();
+		puppet.end_set_params(0.0);
+
+		let drawable_offset = puppet.render_ctx.node_render_ctxs[&hair].drawable_offset;
+		// Static values of the node are the starting point
+		assert_eq!(drawable_offset.opacity, 0.75 * 0.5);
+		assert!(drawable_offset.screen_tint.abs_diff_eq(Vec3::new(0.35, 0.2, 0.3), 1e-6));
+		assert_eq!(drawable_offset.tint, Vec3::ONE);
+
+		// Values are kept until the params are reset
+		puppet.end_set_params(0.0);
+		assert_eq!(puppet.render_ctx.node_render_ctxs[&hair].drawable_offset.opacity, 0.375);
+		puppet.reset_params();
+		puppet.end_set_params(0.0);
+		assert_eq!(puppet.render_ctx.node_render_ctxs[&hair].drawable_offset.opacity, 0.75);
+	}
+
+	/// Custom node drawn like a part, and shifted to the right.
+	#[derive(Clone, Debug)]
+	struct Sprite {
+		part: Part,
+		shift: f32,
+	}
+
+	impl CustomNode for Sprite {
+		fn apply_offsets(&self, node_render_ctx: &mut NodeRenderCtx) {
+			node_render_ctx.trans_offset.translation.x += self.shift;
+		}
+
+		fn part(&self) -> Option<&Part> {
+			Some(&self.part)
+		}
+	}
+
+	#[test]
+	fn test_custom_nodes() {
+		let mut payload = test_payload();
+		let plain = test_puppet();
+		let Some(body) = plain
+			.nodes
+			.get_node(InoxNodeUuid(2))
+			.unwrap()
+			.data
+			.drawn_part()
+			.cloned()
+		else {
+			panic!("the body is a part");
+		};
+
+		let mut sprite = payload["nodes"]["children"][1].clone();
+		sprite["uuid"] = 7.into();
+		sprite["type"] = "Sprite".into();
+		sprite["shift"] = 5.into();
+		sprite["zsort"] = (-1).into();
+		sprite.remove("children");
+		payload["nodes"]["children"].push(sprite).unwrap();
+
+		let mut puppet = deserialize_puppet_ext(&payload, &|node_type, obj| match node_type {
+			"Sprite" => Ok(Sprite {
+				part: body.clone(),
+				shift: obj.get_f32("shift")?,
+			}),
+			_ => Err(InoxParseError::UnknownNodeType(node_type.to_owned())),
+		})
+		.unwrap();
+		let sprite = InoxNodeUuid(7);
+		assert_eq!(puppet.render_ctx.root_drawables_zsorted.last(), Some(&sprite));
+		let RenderCtxKind::Part(ref part_render_ctx) = puppet.render_ctx.node_render_ctxs[&sprite].kind else {
+			panic!("the sprite is drawn as a part");
+		};
+		assert_eq!(part_render_ctx.vert_len, 4);
+
+		puppet.end_set_params(0.0);
+		let trans = puppet.render_ctx.node_render_ctxs[&sprite].trans;
+		let node_x = puppet.nodes.get_node(sprite).unwrap().trans_offset.translation.x;
+		assert_eq!(trans.w_axis.x, node_x + 5.0);
+	}
+}
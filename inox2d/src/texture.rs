@@ -1,13 +1,17 @@
+pub mod bc7;
+
 use std::io;
 
 use image::{ImageBuffer, ImageError, ImageFormat, Rgba};
-use tracing::error;
 
-use crate::model::ModelTexture;
+use crate::model::{ModelTexture, ModelTextureFormat};
 
 use simple_tga_reader::{read_tga, TgaDecodeError, TgaImage};
 
+use self::bc7::{Bc7Error, Bc7Texture};
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureId(pub(crate) usize);
 
 impl TextureId {
@@ -16,10 +20,20 @@ impl TextureId {
 	}
 }
 
+/// Layout of the pixel data of a [`ShallowTexture`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShallowTextureFormat {
+	/// 8-bit RGBA pixels.
+	Rgba8,
+	/// BC7 compressed 4x4 blocks, to be uploaded as is to GPUs that support it.
+	Bc7,
+}
+
 pub struct ShallowTexture {
 	pixels: Vec<u8>,
 	width: u32,
 	height: u32,
+	format: ShallowTextureFormat,
 }
 
 impl ShallowTexture {
@@ -27,6 +41,10 @@ impl ShallowTexture {
 		&self.pixels
 	}
 
+	pub fn format(&self) -> ShallowTextureFormat {
+		self.format
+	}
+
 	pub fn width(&self) -> u32 {
 		self.width
 	}
@@ -42,6 +60,7 @@ impl From<TgaImage> for ShallowTexture {
 			pixels: value.data,
 			width: value.header.width as u32,
 			height: value.header.height as u32,
+			format: ShallowTextureFormat::Rgba8,
 		}
 	}
 }
@@ -52,6 +71,7 @@ impl From<ImageBuffer<Rgba<u8>, Vec<u8>>> for ShallowTexture {
 			pixels: value.to_vec(),
 			width: value.width(),
 			height: value.height(),
+			format: ShallowTextureFormat::Rgba8,
 		}
 	}
 }
@@ -71,24 +91,53 @@ enum DecodeTextureError {
 		#[source]
 		ImageError,
 	),
+
+	#[error("Could not decode BC7 texture")]
+	InvalidBc7(
+		#[from]
+		#[source]
+		Bc7Error,
+	),
 }
 
-fn decode_texture(mtex: ModelTexture) -> Result<ShallowTexture, DecodeTextureError> {
-	if mtex.format == ImageFormat::Tga {
-		let tga_texture = read_tga(&mut io::Cursor::new(&mtex.data))?;
-		Ok(ShallowTexture::from(tga_texture))
-	} else {
-		let img_buf = image::load_from_memory_with_format(&mtex.data, mtex.format)?;
-		Ok(ShallowTexture::from(img_buf.into_rgba8()))
+fn decode_texture(mtex: ModelTexture, keep_bc7: bool) -> Result<ShallowTexture, DecodeTextureError> {
+	match mtex.format {
+		ModelTextureFormat::Png => {
+			let img_buf = image::load_from_memory_with_format(&mtex.data, ImageFormat::Png)?;
+			Ok(ShallowTexture::from(img_buf.into_rgba8()))
+		}
+		ModelTextureFormat::Tga => {
+			let tga_texture = read_tga(&mut io::Cursor::new(&mtex.data))?;
+			Ok(ShallowTexture::from(tga_texture))
+		}
+		ModelTextureFormat::Bc7 => {
+			let bc7_texture = Bc7Texture::from_bytes(&mtex.data)?;
+
+			// GPUs can only sample compressed textures made of whole blocks
+			let whole_blocks = bc7_texture.width().is_multiple_of(4) && bc7_texture.height().is_multiple_of(4);
+			let (pixels, format) = if keep_bc7 && whole_blocks {
+				(bc7_texture.blocks().to_vec(), ShallowTextureFormat::Bc7)
+			} else {
+				(bc7_texture.decode(), ShallowTextureFormat::Rgba8)
+			};
+
+			Ok(ShallowTexture {
+				pixels,
+				width: bc7_texture.width(),
+				height: bc7_texture.height(),
+				format,
+			})
+		}
 	}
 }
 
 #[cfg(target_arch = "wasm32")]
 pub fn decode_model_textures<'a>(
 	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+	keep_bc7: bool,
 ) -> Vec<ShallowTexture> {
 	(model_textures.cloned())
-		.map(decode_texture)
+		.map(|texture| decode_texture(texture, keep_bc7))
 		.inspect(|res| {
 			if let Err(e) = res {
 				tracing::error!("{}", e);
@@ -99,9 +148,12 @@ pub fn decode_model_textures<'a>(
 }
 
 /// Decodes model textures in parallel, using as many threads as we can use minus one.
+///
+/// If `keep_bc7` is set, BC7 textures are not decoded, so that they can be uploaded directly to GPUs supporting them.
 #[cfg(not(target_arch = "wasm32"))]
 pub fn decode_model_textures<'a>(
 	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+	keep_bc7: bool,
 ) -> Vec<ShallowTexture> {
 	use std::sync::mpsc;
 
@@ -132,7 +184,7 @@ pub fn decode_model_textures<'a>(
 			.spawn(move || {
 				// get textures from the thread-local channel, decode them, and send them to the global channel
 				while let Ok((i, texture)) = rx.recv() {
-					match decode_texture(texture) {
+					match decode_texture(texture, keep_bc7) {
 						Ok(decoded) => tx_all.send((i, decoded)).unwrap(),
 						Err(e) => tracing::error!("{}", e),
 					}
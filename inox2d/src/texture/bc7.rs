@@ -0,0 +1,484 @@
+//! Software decoder for BC7 (BPTC) compressed textures.
+//!
+//! In INP files, a BC7 texture is stored as its width and height (big-endian `u32`s),
+//! followed by its 16-byte 4x4 blocks in row-major order.
+
+use crate::formats::{check_limit, LimitExceeded, ParseLimits};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Bc7Error {
+	#[error("BC7 texture header is truncated")]
+	TruncatedHeader,
+	#[error("Expected {expected} bytes of BC7 blocks for a {width}x{height} texture, got {actual}")]
+	InvalidLength {
+		width: u32,
+		height: u32,
+		expected: usize,
+		actual: usize,
+	},
+	#[error("BC7 texture of {width}x{height} pixels is too large")]
+	TooLarge { width: u32, height: u32 },
+	#[error(transparent)]
+	LimitExceeded(#[from] LimitExceeded),
+}
+
+/// BC7 compressed texture, borrowing its blocks.
+#[derive(Clone, Copy, Debug)]
+pub struct Bc7Texture<'a> {
+	width: u32,
+	height: u32,
+	blocks: &'a [u8],
+}
+
+impl<'a> Bc7Texture<'a> {
+	/// Reads a BC7 texture as stored in the texture section of an INP file, within the default [`ParseLimits`].
+	pub fn from_bytes(data: &'a [u8]) -> Result<Self, Bc7Error> {
+		Self::from_bytes_with_limits(data, &ParseLimits::default())
+	}
+
+	/// Reads a BC7 texture as stored in the texture section of an INP file.
+	///
+	/// The pixel count is checked against `limits`, so that [`Bc7Texture::decode`] does not allocate more than allowed.
+	pub fn from_bytes_with_limits(data: &'a [u8], limits: &ParseLimits) -> Result<Self, Bc7Error> {
+		if data.len() < 8 {
+			return Err(Bc7Error::TruncatedHeader);
+		}
+
+		let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+		let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+		let blocks = &data[8..];
+
+		// Both the blocks and the decoded RGBA pixels must be addressable
+		let too_large = || Bc7Error::TooLarge { width, height };
+		let pixels = (width as usize).checked_mul(height as usize).ok_or_else(too_large)?;
+		check_limit("texture pixel count", pixels, limits.max_texture_pixels)?;
+		pixels.checked_mul(4).ok_or_else(too_large)?;
+		let expected = ((width as usize).div_ceil(4))
+			.checked_mul((height as usize).div_ceil(4))
+			.and_then(|n| n.checked_mul(16))
+			.ok_or_else(too_large)?;
+
+		if blocks.len() != expected {
+			return Err(Bc7Error::InvalidLength {
+				width,
+				height,
+				expected,
+				actual: blocks.len(),
+			});
+		}
+
+		Ok(Self { width, height, blocks })
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// Compressed blocks, ready to be uploaded to a GPU that supports BC7.
+	pub fn blocks(&self) -> &'a [u8] {
+		self.blocks
+	}
+
+	/// Decodes the texture into RGBA8 pixels.
+	pub fn decode(&self) -> Vec<u8> {
+		let width = self.width as usize;
+		let height = self.height as usize;
+		let blocks_x = width.div_ceil(4);
+
+		let mut pixels = vec![0_u8; width * height * 4];
+		for (i, block) in self.blocks.chunks_exact(16).enumerate() {
+			let (bx, by) = (i % blocks_x * 4, i / blocks_x * 4);
+			let texels = decode_block(block.try_into().unwrap());
+
+			// Blocks on the right and bottom edges may overflow the texture
+			for y in 0..4.min(height - by) {
+				for x in 0..4.min(width - bx) {
+					let offset = ((by + y) * width + bx + x) * 4;
+					pixels[offset..offset + 4].copy_from_slice(&texels[y * 4 + x]);
+				}
+			}
+		}
+
+		pixels
+	}
+}
+
+struct ModeInfo {
+	subsets: usize,
+	partition_bits: u32,
+	rotation_bits: u32,
+	index_selection_bits: u32,
+	color_bits: u32,
+	alpha_bits: u32,
+	endpoint_pbits: bool,
+	shared_pbits: bool,
+	index_bits: u32,
+	index_bits2: u32,
+}
+
+#[rustfmt::skip]
+const MODES: [ModeInfo; 8] = [
+	ModeInfo { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_pbits: true,  shared_pbits: false, index_bits: 3, index_bits2: 0 },
+	ModeInfo { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_pbits: false, shared_pbits: true,  index_bits: 3, index_bits2: 0 },
+	ModeInfo { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index_bits2: 0 },
+	ModeInfo { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_pbits: true,  shared_pbits: false, index_bits: 2, index_bits2: 0 },
+	ModeInfo { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index_bits2: 3 },
+	ModeInfo { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index_bits2: 2 },
+	ModeInfo { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_pbits: true,  shared_pbits: false, index_bits: 4, index_bits2: 0 },
+	ModeInfo { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_pbits: true,  shared_pbits: false, index_bits: 2, index_bits2: 0 },
+];
+
+/// Subset of each texel for 2-subset partitions, one bit per texel.
+#[rustfmt::skip]
+const PARTITIONS_2: [u16; 64] = [
+	0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+	0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+	0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+	0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+	0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+	0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+	0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+	0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+/// Subset of each texel for 3-subset partitions, two bits per texel.
+#[rustfmt::skip]
+const PARTITIONS_3: [u32; 64] = [
+	0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+	0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+	0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+	0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+	0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+	0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+	0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+	0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// Anchor texel of the second subset of 2-subset partitions.
+#[rustfmt::skip]
+const ANCHORS_2: [usize; 64] = [
+	15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+	15,  2,  8,  2,  2,  8,  8, 15,  2,  8,  2,  2,  8,  8,  2,  2,
+	15, 15,  6,  8,  2,  8, 15, 15,  2,  8,  2,  2,  2, 15, 15,  6,
+	 6,  2,  6,  8, 15, 15,  2,  2, 15, 15, 15, 15, 15,  2,  2, 15,
+];
+
+/// Anchor texel of the second subset of 3-subset partitions.
+#[rustfmt::skip]
+const ANCHORS_3_SECOND: [usize; 64] = [
+	 3,  3, 15, 15,  8,  3, 15, 15,  8,  8,  6,  6,  6,  5,  3,  3,
+	 3,  3,  8, 15,  3,  3,  6, 10,  5,  8,  8,  6,  8,  5, 15, 15,
+	 8, 15,  3,  5,  6, 10,  8, 15, 15,  3, 15,  5, 15, 15, 15, 15,
+	 3, 15,  5,  5,  5,  8,  5, 10,  5, 10,  8, 13, 15, 12,  3,  3,
+];
+
+/// Anchor texel of the third subset of 3-subset partitions.
+#[rustfmt::skip]
+const ANCHORS_3_THIRD: [usize; 64] = [
+	15,  8,  8,  3, 15, 15,  3,  8, 15, 15, 15, 15, 15, 15, 15,  8,
+	15,  8, 15,  3, 15,  8, 15,  8,  3, 15,  6, 10, 15, 15, 10,  8,
+	15,  3, 15, 10, 10,  8,  9, 10,  6, 15,  8, 15,  3,  6,  6,  8,
+	15,  3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,  3, 15, 15,  8,
+];
+
+const WEIGHTS_2: [u16; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u16; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u16; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Reads bits of a block, least significant bit first.
+struct BitReader {
+	bits: u128,
+	pos: u32,
+}
+
+impl BitReader {
+	fn read(&mut self, n: u32) -> u8 {
+		let value = (self.bits >> self.pos) & ((1 << n) - 1);
+		self.pos += n;
+		value as u8
+	}
+}
+
+fn subset_of(subsets: usize, partition: usize, texel: usize) -> usize {
+	match subsets {
+		2 => (PARTITIONS_2[partition] >> texel) as usize & 1,
+		3 => (PARTITIONS_3[partition] >> (2 * texel)) as usize & 3,
+		_ => 0,
+	}
+}
+
+fn is_anchor(subsets: usize, partition: usize, texel: usize) -> bool {
+	texel == 0
+		|| match subsets {
+			2 => texel == ANCHORS_2[partition],
+			3 => texel == ANCHORS_3_SECOND[partition] || texel == ANCHORS_3_THIRD[partition],
+			_ => false,
+		}
+}
+
+/// Expands a quantized endpoint component to 8 bits by replicating its highest bits.
+fn unquantize(value: u8, precision: u32) -> u8 {
+	if precision >= 8 {
+		return value;
+	}
+	let value = value << (8 - precision);
+	value | (value >> precision)
+}
+
+fn interpolate(e0: u8, e1: u8, index: u8, index_bits: u32) -> u8 {
+	let weight = match index_bits {
+		2 => WEIGHTS_2[index as usize],
+		3 => WEIGHTS_3[index as usize],
+		_ => WEIGHTS_4[index as usize],
+	};
+
+	(((64 - weight) * e0 as u16 + weight * e1 as u16 + 32) >> 6) as u8
+}
+
+/// Decodes one 4x4 block into RGBA8 texels, in row-major order.
+///
+/// Blocks with a reserved mode decode to transparent black.
+pub fn decode_block(block: &[u8; 16]) -> [[u8; 4]; 16] {
+	let bits = u128::from_le_bytes(*block);
+
+	let mode = bits.trailing_zeros();
+	if mode >= 8 {
+		return [[0; 4]; 16];
+	}
+
+	let info = &MODES[mode as usize];
+	let mut reader = BitReader { bits, pos: mode + 1 };
+
+	let partition = reader.read(info.partition_bits) as usize;
+	let rotation = reader.read(info.rotation_bits);
+	let index_selection = reader.read(info.index_selection_bits);
+
+	// Endpoints are stored channel by channel
+	let n_endpoints = info.subsets * 2;
+	let mut endpoints = [[0_u8; 4]; 6];
+	for channel in 0..3 {
+		for endpoint in &mut endpoints[..n_endpoints] {
+			endpoint[channel] = reader.read(info.color_bits);
+		}
+	}
+	for endpoint in &mut endpoints[..n_endpoints] {
+		endpoint[3] = reader.read(info.alpha_bits);
+	}
+
+	// P-bits are an extra shared least significant bit for all channels of an endpoint
+	let has_pbits = info.endpoint_pbits || info.shared_pbits;
+	if info.endpoint_pbits {
+		for endpoint in &mut endpoints[..n_endpoints] {
+			let pbit = reader.read(1);
+			endpoint.iter_mut().for_each(|c| *c = (*c << 1) | pbit);
+		}
+	} else if info.shared_pbits {
+		for subset in endpoints[..n_endpoints].chunks_exact_mut(2) {
+			let pbit = reader.read(1);
+			for endpoint in subset {
+				endpoint.iter_mut().for_each(|c| *c = (*c << 1) | pbit);
+			}
+		}
+	}
+
+	let color_precision = info.color_bits + has_pbits as u32;
+	let alpha_precision = info.alpha_bits + has_pbits as u32;
+	for endpoint in &mut endpoints[..n_endpoints] {
+		for c in &mut endpoint[..3] {
+			*c = unquantize(*c, color_precision);
+		}
+		endpoint[3] = match info.alpha_bits {
+			0 => 255,
+			_ => unquantize(endpoint[3], alpha_precision),
+		};
+	}
+
+	// Anchor texels have their index's most significant bit implicitly set to 0
+	let mut indices = [0_u8; 16];
+	for (texel, index) in indices.iter_mut().enumerate() {
+		let anchor = is_anchor(info.subsets, partition, texel);
+		*index = reader.read(info.index_bits - anchor as u32);
+	}
+
+	let mut indices2 = [0_u8; 16];
+	if info.index_bits2 > 0 {
+		for (texel, index) in indices2.iter_mut().enumerate() {
+			*index = reader.read(info.index_bits2 - (texel == 0) as u32);
+		}
+	}
+
+	let mut texels = [[0_u8; 4]; 16];
+	for (i, texel) in texels.iter_mut().enumerate() {
+		let subset = subset_of(info.subsets, partition, i);
+		let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+
+		let ((color_index, color_bits), (alpha_index, alpha_bits)) = match (info.index_bits2, index_selection) {
+			(0, _) => ((indices[i], info.index_bits), (indices[i], info.index_bits)),
+			(_, 0) => ((indices[i], info.index_bits), (indices2[i], info.index_bits2)),
+			(_, _) => ((indices2[i], info.index_bits2), (indices[i], info.index_bits)),
+		};
+
+		for c in 0..3 {
+			texel[c] = interpolate(e0[c], e1[c], color_index, color_bits);
+		}
+		texel[3] = interpolate(e0[3], e1[3], alpha_index, alpha_bits);
+
+		match rotation {
+			1 => texel.swap(0, 3),
+			2 => texel.swap(1, 3),
+			3 => texel.swap(2, 3),
+			_ => (),
+		}
+	}
+
+	texels
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Packs bit fields into a block, least significant bit first.
+	#[derive(Default)]
+	struct BitWriter {
+		bits: u128,
+		pos: u32,
+	}
+
+	impl BitWriter {
+		fn write(&mut self, value: u32, n: u32) -> &mut Self {
+			self.bits |= (value as u128 & ((1 << n) - 1)) << self.pos;
+			self.pos += n;
+			self
+		}
+
+		fn finish(&self) -> [u8; 16] {
+			assert_eq!(self.pos, 128, "block is not fully written");
+			self.bits.to_le_bytes()
+		}
+	}
+
+	#[test]
+	fn test_oversized_header() {
+		let data = [0xff_u8; 8];
+		assert!(matches!(
+			Bc7Texture::from_bytes_with_limits(&data, &ParseLimits::UNLIMITED),
+			Err(Bc7Error::TooLarge { .. })
+		));
+		assert!(matches!(Bc7Texture::from_bytes(&data), Err(Bc7Error::LimitExceeded(_))));
+
+		let mut data = vec![0_u8; 8 + 16];
+		data[3] = 4;
+		data[7] = 4;
+		assert!(Bc7Texture::from_bytes(&data).is_ok());
+		let limits = ParseLimits {
+			max_texture_pixels: 15,
+			..Default::default()
+		};
+		assert!(matches!(
+			Bc7Texture::from_bytes_with_limits(&data, &limits),
+			Err(Bc7Error::LimitExceeded(_))
+		));
+	}
+
+	#[test]
+	fn test_reserved_mode() {
+		assert_eq!(decode_block(&[0; 16]), [[0; 4]; 16]);
+	}
+
+	#[test]
+	fn test_mode6_gradient() {
+		// Mode 6: 7-bit RGBA endpoints with one p-bit each, and 4-bit indices
+		let mut writer = BitWriter::default();
+		writer.write(1 << 6, 7);
+		for (e0, e1) in [(0, 127), (10, 20), (127, 0), (127, 127)] {
+			writer.write(e0, 7).write(e1, 7);
+		}
+		writer.write(0, 1).write(1, 1);
+		// The anchor texel only has 3 index bits
+		writer.write(0, 3);
+		for i in 1..16 {
+			writer.write(i, 4);
+		}
+
+		let texels = decode_block(&writer.finish());
+		for (i, texel) in texels.iter().enumerate() {
+			let w = WEIGHTS_4[i];
+			let lerp = |e0: u16, e1: u16| (((64 - w) * e0 + w * e1 + 32) >> 6) as u8;
+			assert_eq!(
+				*texel,
+				[lerp(0, 255), lerp(20, 41), lerp(254, 1), lerp(254, 255)],
+				"texel {i}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_mode5_rotation() {
+		// Mode 5: rotation, 7-bit color and 8-bit alpha endpoints, separate 2-bit color and alpha indices
+		let mut writer = BitWriter::default();
+		writer.write(1 << 5, 6);
+		// Swap alpha and green
+		writer.write(2, 2);
+		for (e0, e1) in [(127, 127), (0, 0), (64, 64)] {
+			writer.write(e0, 7).write(e1, 7);
+		}
+		writer.write(0, 8).write(255, 8);
+		writer.write(0, 31);
+		writer.write(0, 1);
+		for _ in 1..16 {
+			writer.write(3, 2);
+		}
+
+		let texels = decode_block(&writer.finish());
+		assert_eq!(texels[0], [255, 0, 129, 0]);
+		for texel in &texels[1..] {
+			assert_eq!(*texel, [255, 255, 129, 0]);
+		}
+	}
+
+	#[test]
+	fn test_mode1_partitions() {
+		// Mode 1: two subsets with 6-bit endpoints, shared p-bits and 3-bit indices
+		let mut writer = BitWriter::default();
+		writer.write(1 << 1, 2);
+		// Partition 13: top half in subset 0, bottom half in subset 1
+		writer.write(13, 6);
+		for _ in 0..3 {
+			writer.write(0, 6).write(0, 6).write(63, 6).write(63, 6);
+		}
+		writer.write(0, 1).write(1, 1);
+		// Anchors are texels 0 and 15
+		writer.write(0, 2);
+		for _ in 1..15 {
+			writer.write(0, 3);
+		}
+		writer.write(0, 2);
+
+		let texels = decode_block(&writer.finish());
+		for (i, texel) in texels.iter().enumerate() {
+			let expected = if i < 8 { [0, 0, 0, 255] } else { [255; 4] };
+			assert_eq!(*texel, expected, "texel {i}");
+		}
+	}
+
+	#[test]
+	fn test_texture_edges() {
+		let mut data = Vec::new();
+		data.extend_from_slice(&5_u32.to_be_bytes());
+		data.extend_from_slice(&3_u32.to_be_bytes());
+		data.extend_from_slice(&[0; 32]);
+
+		let texture = Bc7Texture::from_bytes(&data).unwrap();
+		assert_eq!(texture.decode().len(), 5 * 3 * 4);
+
+		assert!(matches!(
+			Bc7Texture::from_bytes(&data[..40 - 16]),
+			Err(Bc7Error::InvalidLength { expected: 32, .. })
+		));
+	}
+}
@@ -0,0 +1,220 @@
+//! Corpus of malformed INP files, checking that parsing them returns errors instead of panicking.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use inox2d::formats::inp::{parse_inp, parse_inp_with_diagnostics, write_inp, LazyInp, ParseInpError};
+use inox2d::formats::payload::{deserialize_puppet, InoxParseError, ParseDiagnostics, ParseMode};
+use inox2d::formats::{LimitExceeded, ParseLimits};
+use inox2d::model::{Model, ModelTexture, ModelTextureFormat, VendorData};
+use json::JsonValue;
+
+const PUPPET_JSON: &str = include_str!("fixtures/puppet.json");
+
+/// Small deterministic xorshift generator, so that failures are reproducible.
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	fn below(&mut self, n: usize) -> usize {
+		(self.next() % n as u64) as usize
+	}
+}
+
+fn valid_inp() -> Vec<u8> {
+	let model = Model {
+		puppet: deserialize_puppet(&json::parse(PUPPET_JSON).unwrap()).unwrap(),
+		textures: vec![
+			ModelTexture {
+				format: ModelTextureFormat::Png,
+				data: Arc::from(&b"not really a png"[..]),
+			},
+			ModelTexture {
+				format: ModelTextureFormat::Bc7,
+				data: Arc::from(&[0_u8; 24][..]),
+			},
+		],
+		vendors: vec![VendorData {
+			name: "com.inochi2d.inochi-session.bindings".to_owned(),
+			payload: json::array![{ "name": "Head", "value": 0.5 }],
+		}],
+	};
+
+	let mut data = Vec::new();
+	write_inp(&model, &mut data).unwrap();
+	data
+}
+
+/// Builds an INP file around a raw payload, without textures.
+fn inp_with_payload(payload: &[u8]) -> Vec<u8> {
+	let mut data = b"TRNSRTS\0".to_vec();
+	data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+	data.extend_from_slice(payload);
+	data.extend_from_slice(b"TEX_SECT");
+	data.extend_from_slice(&0_u32.to_be_bytes());
+	data
+}
+
+/// Parses the data in every supported way. Only panics matter, errors are expected.
+fn parse_all_ways(data: &[u8]) {
+	if let Ok(model) = parse_inp(data) {
+		let _ = model.validate();
+	}
+
+	let mut diag = ParseDiagnostics::new(ParseMode::Strict).with_validation();
+	let _ = parse_inp_with_diagnostics(data, &mut diag);
+
+	if let Ok(mut lazy) = LazyInp::open(Cursor::new(data)) {
+		let _ = lazy.read_thumbnail();
+		for id in 0..lazy.textures().len() {
+			let _ = lazy.texture_bytes(id);
+			let _ = lazy.read_texture(id);
+		}
+	}
+}
+
+#[test]
+fn test_mutated_bytes() {
+	let valid = valid_inp();
+	let mut rng = Rng(0x1a2b_3c4d_5e6f_7081);
+
+	for _ in 0..2000 {
+		let mut data = valid.clone();
+		match rng.below(4) {
+			// flip a few bytes
+			0 => {
+				for _ in 0..=rng.below(8) {
+					let i = rng.below(data.len());
+					data[i] = rng.next() as u8;
+				}
+			}
+			// overwrite a length-like word with a big value
+			1 => {
+				let i = rng.below(data.len() - 4);
+				let value = [u32::MAX, u32::MAX / 2, 1 << 24, rng.next() as u32][rng.below(4)];
+				data[i..i + 4].copy_from_slice(&value.to_be_bytes());
+			}
+			// truncate
+			2 => data.truncate(rng.below(data.len())),
+			// random garbage after the magic bytes
+			_ => {
+				data.truncate(8);
+				data.extend((0..rng.below(256)).map(|_| rng.next() as u8));
+			}
+		}
+		parse_all_ways(&data);
+	}
+}
+
+/// Counts the values in a JSON tree.
+fn count_values(value: &JsonValue) -> usize {
+	1 + match value {
+		JsonValue::Object(obj) => obj.iter().map(|(_, value)| count_values(value)).sum(),
+		JsonValue::Array(arr) => arr.iter().map(count_values).sum(),
+		_ => 0,
+	}
+}
+
+/// Gets the `n`th value of a JSON tree in pre-order.
+fn nth_value<'a>(value: &'a mut JsonValue, n: &mut usize) -> Option<&'a mut JsonValue> {
+	if *n == 0 {
+		return Some(value);
+	}
+	*n -= 1;
+
+	match value {
+		JsonValue::Object(obj) => obj.iter_mut().find_map(|(_, value)| nth_value(value, n)),
+		JsonValue::Array(arr) => arr.iter_mut().find_map(|value| nth_value(value, n)),
+		_ => None,
+	}
+}
+
+#[test]
+fn test_mutated_payload() {
+	let replacements = [
+		JsonValue::Null,
+		true.into(),
+		(-1).into(),
+		0.into(),
+		1e300.into(),
+		(-1e300).into(),
+		u32::MAX.into(),
+		70000.into(),
+		"".into(),
+		"Unknown".into(),
+		json::array![],
+		json::array![[]],
+		json::array![1, 2, 3],
+		json::array![[0], [0]],
+		json::object! {},
+	];
+
+	let valid = json::parse(PUPPET_JSON).unwrap();
+	let value_count = count_values(&valid);
+	let mut rng = Rng(0x0123_4567_89ab_cdef);
+
+	for _ in 0..2000 {
+		let mut payload = valid.clone();
+		for _ in 0..=rng.below(3) {
+			let mut n = rng.below(value_count);
+			if let Some(value) = nth_value(&mut payload, &mut n) {
+				*value = replacements[rng.below(replacements.len())].clone();
+			}
+		}
+		parse_all_ways(&inp_with_payload(json::stringify(payload).as_bytes()));
+	}
+}
+
+#[test]
+fn test_crafted_files() {
+	// payload length far beyond the actual data
+	let mut data = b"TRNSRTS\0".to_vec();
+	data.extend_from_slice(&u32::MAX.to_be_bytes());
+	assert!(matches!(
+		parse_inp(data.as_slice()),
+		Err(ParseInpError::LimitExceeded(_))
+	));
+
+	// unknown physics model
+	let mut payload = json::parse(PUPPET_JSON).unwrap();
+	payload["nodes"]["children"][0]["children"][1]["model_type"] = "Unknown".into();
+	let data = inp_with_payload(json::stringify(payload).as_bytes());
+	assert!(matches!(parse_inp(data.as_slice()), Err(ParseInpError::InoxParse(_))));
+
+	// well-formed meshes with more vertices in total than u16 indices can address
+	let mut payload = json::parse(PUPPET_JSON).unwrap();
+	let hair = &mut payload["nodes"]["children"][0]["children"][0];
+	hair["mesh"]["verts"] = JsonValue::Array(vec![0.into(); 2 * 70000]);
+	hair["mesh"]["uvs"] = JsonValue::Array(vec![0.into(); 2 * 70000]);
+	let data = inp_with_payload(json::stringify(payload).as_bytes());
+	let Err(ParseInpError::InoxParse(InoxParseError::LimitExceeded(LimitExceeded { what, .. }))) =
+		parse_inp(data.as_slice())
+	else {
+		panic!("oversized meshes were not rejected");
+	};
+	assert_eq!(what, "mesh vertex count");
+	assert!(LazyInp::open(Cursor::new(data)).is_err());
+
+	// nesting deep enough to overflow the stack when recursing
+	let payload = format!(r#"{{"nodes":{}0{}}}"#, "[".repeat(100_000), "]".repeat(100_000));
+	let data = inp_with_payload(payload.as_bytes());
+	let Err(ParseInpError::LimitExceeded(LimitExceeded { what, .. })) = parse_inp(data.as_slice()) else {
+		panic!("deep nesting was not rejected");
+	};
+	assert_eq!(what, "JSON nesting depth");
+
+	// texture count over a custom limit
+	let limits = ParseLimits {
+		max_texture_count: 1,
+		..ParseLimits::default()
+	};
+	let mut diag = ParseDiagnostics::default().with_limits(limits);
+	let result = parse_inp_with_diagnostics(valid_inp().as_slice(), &mut diag);
+	assert!(matches!(result, Err(ParseInpError::LimitExceeded(_))));
+}